@@ -40,6 +40,8 @@ pub enum LoroError {
     AutoCommitNotStarted,
     #[error("The doc is already dropped")]
     DocDropError,
+    #[error("The checksum does not match. The encoded data is corrupted")]
+    Corrupted,
     // #[error("the data for key `{0}` is not available")]
     // Redaction(String),
     // #[error("invalid header (expected {expected:?}, found {found:?})")]
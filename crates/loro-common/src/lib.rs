@@ -11,7 +11,7 @@ mod value;
 
 pub use error::{LoroError, LoroResult, LoroTreeError};
 pub use span::*;
-pub use value::LoroValue;
+pub use value::{LoroValue, PathSegment};
 
 use zerovec::ule::AsULE;
 pub type PeerID = u64;
@@ -9,6 +9,10 @@ use crate::ContainerID;
 /// [LoroValue] is used to represents the state of CRDT at a given version.
 ///
 /// This struct is cheap to clone, the time complexity is O(1).
+///
+/// Equality is structural: maps are compared key by key regardless of the insertion order,
+/// lists are compared by position. Containers are compared by id, so compare the deep
+/// values of two docs (`get_deep_value`) to check whether they have converged.
 #[derive(Debug, PartialEq, Clone, EnumAsInner, Default)]
 pub enum LoroValue {
     #[default]
@@ -39,6 +43,42 @@ impl LoroValue {
             _ => None,
         }
     }
+
+    /// Get the nested value by following `path` through maps and lists.
+    ///
+    /// Return `None` if a segment is missing or doesn't match the type of the value,
+    /// e.g. a key on a list.
+    pub fn get_by_path(&self, path: &[PathSegment]) -> Option<&LoroValue> {
+        path.iter().try_fold(self, |value, segment| match segment {
+            PathSegment::Key(key) => value.get_by_key(key),
+            PathSegment::Index(index) => value.get_by_index(*index),
+        })
+    }
+}
+
+/// A segment of the path in [LoroValue::get_by_path].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
 }
 
 impl Index<&str> for LoroValue {
@@ -387,6 +427,11 @@ pub mod wasm {
     }
 }
 
+/// In human-readable formats like JSON, maps become objects, lists and binaries become
+/// arrays, and a container is tagged as `{"Container": <ContainerID>}`. This is the
+/// plain JSON form of the value, so binaries and containers are read back as lists and
+/// maps. Non human-readable formats like postcard keep the variant, so all the values
+/// round-trip.
 impl Serialize for LoroValue {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -4,7 +4,7 @@ mod sync {
 
     use super::*;
     use bench_utils::{get_automerge_actions, TextAction};
-    use loro_internal::LoroDoc;
+    use loro_internal::{configure::Configure, LoroDoc};
 
     pub fn b4(c: &mut Criterion) {
         let actions = get_automerge_actions();
@@ -43,6 +43,37 @@ mod sync {
             })
         });
     }
+
+    pub fn single_writer(c: &mut Criterion) {
+        let actions = get_automerge_actions();
+        let mut b = c.benchmark_group("sync_single_writer");
+        b.sample_size(10);
+        for single_writer in [false, true] {
+            b.bench_function(format!("single_writer={}", single_writer), |b| {
+                b.iter(|| {
+                    let c1 = LoroDoc::new();
+                    c1.set_peer_id(1).unwrap();
+                    let c2 = LoroDoc::new();
+                    c2.set_peer_id(2).unwrap();
+                    c2.set_configure(Configure {
+                        single_writer,
+                        ..Default::default()
+                    });
+                    let t1 = c1.get_text("text");
+                    for action in actions.iter().take(2000) {
+                        let TextAction { pos, ins, del } = action;
+                        let mut txn = c1.txn().unwrap();
+                        t1.delete(&mut txn, *pos, *del).unwrap();
+                        t1.insert(&mut txn, *pos, ins).unwrap();
+                        txn.commit().unwrap();
+
+                        let update = c1.export_from(&c2.oplog_vv());
+                        c2.import(&update).unwrap();
+                    }
+                })
+            });
+        }
+    }
 }
 #[cfg(feature = "test_utils")]
 mod run {
@@ -141,7 +172,13 @@ mod import {
 pub fn dumb(_c: &mut Criterion) {}
 
 #[cfg(feature = "test_utils")]
-criterion_group!(benches, run::b4, sync::b4, import::causal_iter);
+criterion_group!(
+    benches,
+    run::b4,
+    sync::b4,
+    sync::single_writer,
+    import::causal_iter
+);
 #[cfg(not(feature = "test_utils"))]
 criterion_group!(benches, dumb);
 criterion_main!(benches);
@@ -19,6 +19,7 @@ use crate::{
     },
     id::Counter,
     op::{InnerContent, ListSlice, Op, RawOp, RawOpContent, SliceRange},
+    utils::lock::LockOrRecover,
     LoroValue,
 };
 
@@ -36,6 +37,11 @@ struct InnerSharedArena {
     parents: Mutex<FxHashMap<ContainerIdx, Option<ContainerIdx>>>,
     values: Mutex<Vec<LoroValue>>,
     root_c_idx: Mutex<Vec<ContainerIdx>>,
+    /// All the inserted text, append-only.
+    ///
+    /// The ops in [OpLog] refer to it by unicode ranges and the richtext state
+    /// holds slices of it, so deleted text is never freed while the doc is alive.
+    /// It cannot be compacted without rewriting the history.
     str: Mutex<StrArena>,
 }
 
@@ -192,94 +198,113 @@ impl<'a> OpConverter<'a> {
 
 impl SharedArena {
     pub fn register_container(&self, id: &ContainerID) -> ContainerIdx {
-        let mut container_id_to_idx = self.inner.container_id_to_idx.lock().unwrap();
+        let mut container_id_to_idx = self.inner.container_id_to_idx.lock_or_recover();
         if let Some(&idx) = container_id_to_idx.get(id) {
             return idx;
         }
 
-        let mut container_idx_to_id = self.inner.container_idx_to_id.lock().unwrap();
+        let mut container_idx_to_id = self.inner.container_idx_to_id.lock_or_recover();
         let idx = container_idx_to_id.len();
         container_idx_to_id.push(id.clone());
         let idx = ContainerIdx::from_index_and_type(idx as u32, id.container_type());
         container_id_to_idx.insert(id.clone(), idx);
         if id.is_root() {
-            self.inner.root_c_idx.lock().unwrap().push(idx);
-            self.inner.parents.lock().unwrap().insert(idx, None);
-            self.inner.depth.lock().unwrap().push(1);
+            self.inner.root_c_idx.lock_or_recover().push(idx);
+            self.inner.parents.lock_or_recover().insert(idx, None);
+            self.inner.depth.lock_or_recover().push(1);
         } else {
-            self.inner.depth.lock().unwrap().push(0);
+            self.inner.depth.lock_or_recover().push(0);
         }
         idx
     }
 
     pub fn get_container_id(&self, idx: ContainerIdx) -> Option<ContainerID> {
-        let lock = self.inner.container_idx_to_id.lock().unwrap();
+        let lock = self.inner.container_idx_to_id.lock_or_recover();
         lock.get(idx.to_index() as usize).cloned()
     }
 
     pub fn id_to_idx(&self, id: &ContainerID) -> Option<ContainerIdx> {
         self.inner
             .container_id_to_idx
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get(id)
             .copied()
     }
 
     #[inline]
     pub fn idx_to_id(&self, id: ContainerIdx) -> Option<ContainerID> {
-        let lock = self.inner.container_idx_to_id.lock().unwrap();
+        let lock = self.inner.container_idx_to_id.lock_or_recover();
         lock.get(id.to_index() as usize).cloned()
     }
 
     #[inline]
     pub fn with_idx_to_id<R>(&self, f: impl FnOnce(&Vec<ContainerID>) -> R) -> R {
-        let lock = self.inner.container_idx_to_id.lock().unwrap();
+        let lock = self.inner.container_idx_to_id.lock_or_recover();
         f(&lock)
     }
 
     pub fn alloc_str(&self, str: &str) -> StrAllocResult {
-        let mut text_lock = self.inner.str.lock().unwrap();
+        let mut text_lock = self.inner.str.lock_or_recover();
         _alloc_str(&mut text_lock, str)
     }
 
     /// return slice and unicode index
     pub fn alloc_str_with_slice(&self, str: &str) -> (BytesSlice, usize) {
-        let mut text_lock = self.inner.str.lock().unwrap();
+        let mut text_lock = self.inner.str.lock_or_recover();
         let start = text_lock.len_bytes();
         let unicode_start = text_lock.len_unicode();
         text_lock.alloc(str);
         (text_lock.slice_bytes(start..), unicode_start)
     }
 
+    /// return slice, unicode index and unicode len
+    pub fn alloc_chars_with_slice(
+        &self,
+        chars: impl Iterator<Item = char>,
+    ) -> (BytesSlice, usize, usize) {
+        // collect the chars before locking, the iterator may read the arena
+        let str: String = chars.collect();
+        let mut text_lock = self.inner.str.lock_or_recover();
+        let start = text_lock.len_bytes();
+        let unicode_start = text_lock.len_unicode();
+        text_lock.alloc(&str);
+        let unicode_len = text_lock.len_unicode() - unicode_start;
+        (text_lock.slice_bytes(start..), unicode_start, unicode_len)
+    }
+
     /// alloc str without extra info
     pub fn alloc_str_fast(&self, bytes: &[u8]) {
-        let mut text_lock = self.inner.str.lock().unwrap();
+        let mut text_lock = self.inner.str.lock_or_recover();
         text_lock.alloc(std::str::from_utf8(bytes).unwrap());
     }
 
     #[inline]
     pub fn utf16_len(&self) -> usize {
-        self.inner.str.lock().unwrap().len_utf16()
+        self.inner.str.lock_or_recover().len_utf16()
+    }
+
+    #[inline]
+    pub fn str_len_bytes(&self) -> usize {
+        self.inner.str.lock_or_recover().len_bytes()
     }
 
     #[inline]
     pub fn alloc_value(&self, value: LoroValue) -> usize {
-        let mut values_lock = self.inner.values.lock().unwrap();
+        let mut values_lock = self.inner.values.lock_or_recover();
         _alloc_value(&mut values_lock, value)
     }
 
     #[inline]
     pub fn alloc_values(&self, values: impl Iterator<Item = LoroValue>) -> std::ops::Range<usize> {
-        let mut values_lock = self.inner.values.lock().unwrap();
+        let mut values_lock = self.inner.values.lock_or_recover();
         _alloc_values(&mut values_lock, values)
     }
 
     #[inline]
     pub fn set_parent(&self, child: ContainerIdx, parent: Option<ContainerIdx>) {
-        let parents = &mut self.inner.parents.lock().unwrap();
+        let parents = &mut self.inner.parents.lock_or_recover();
         parents.insert(child, parent);
-        let mut depth = self.inner.depth.lock().unwrap();
+        let mut depth = self.inner.depth.lock_or_recover();
 
         match parent {
             Some(p) => {
@@ -297,7 +322,7 @@ impl SharedArena {
 
     pub fn log_hierarchy(&self) {
         if cfg!(debug_assertions) {
-            for (c, p) in self.inner.parents.lock().unwrap().iter() {
+            for (c, p) in self.inner.parents.lock_or_recover().iter() {
                 debug_log::debug_log!(
                     "container {:?} {:?} {:?}",
                     c,
@@ -311,8 +336,7 @@ impl SharedArena {
     pub fn get_parent(&self, child: ContainerIdx) -> Option<ContainerIdx> {
         self.inner
             .parents
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get(&child)
             .copied()
             .flatten()
@@ -333,17 +357,17 @@ impl SharedArena {
 
     #[inline]
     pub fn slice_by_unicode(&self, range: impl RangeBounds<usize>) -> BytesSlice {
-        self.inner.str.lock().unwrap().slice_by_unicode(range)
+        self.inner.str.lock_or_recover().slice_by_unicode(range)
     }
 
     #[inline]
     pub fn slice_by_utf8(&self, range: impl RangeBounds<usize>) -> BytesSlice {
-        self.inner.str.lock().unwrap().slice_bytes(range)
+        self.inner.str.lock_or_recover().slice_bytes(range)
     }
 
     #[inline]
     pub fn slice_str_by_unicode_range(&self, range: Range<usize>) -> String {
-        let mut s = self.inner.str.lock().unwrap();
+        let mut s = self.inner.str.lock_or_recover();
         let s: &mut StrArena = &mut s;
         let mut ans = String::with_capacity(range.len());
         ans.push_str(s.slice_str_by_unicode(range));
@@ -352,29 +376,29 @@ impl SharedArena {
 
     #[inline]
     pub fn with_text_slice(&self, range: Range<usize>, mut f: impl FnMut(&str)) {
-        f(self.inner.str.lock().unwrap().slice_str_by_unicode(range))
+        f(self.inner.str.lock_or_recover().slice_str_by_unicode(range))
     }
 
     #[inline]
     pub fn get_value(&self, idx: usize) -> Option<LoroValue> {
-        self.inner.values.lock().unwrap().get(idx).cloned()
+        self.inner.values.lock_or_recover().get(idx).cloned()
     }
 
     #[inline]
     pub fn get_values(&self, range: Range<usize>) -> Vec<LoroValue> {
-        (self.inner.values.lock().unwrap()[range]).to_vec()
+        (self.inner.values.lock_or_recover()[range]).to_vec()
     }
 
     #[inline(always)]
     pub(crate) fn with_op_converter<R>(&self, f: impl FnOnce(&mut OpConverter) -> R) -> R {
         let mut op_converter = OpConverter {
-            container_idx_to_id: self.inner.container_idx_to_id.lock().unwrap(),
-            container_id_to_idx: self.inner.container_id_to_idx.lock().unwrap(),
-            container_idx_depth: self.inner.depth.lock().unwrap(),
-            str: self.inner.str.lock().unwrap(),
-            values: self.inner.values.lock().unwrap(),
-            root_c_idx: self.inner.root_c_idx.lock().unwrap(),
-            parents: self.inner.parents.lock().unwrap(),
+            container_idx_to_id: self.inner.container_idx_to_id.lock_or_recover(),
+            container_id_to_idx: self.inner.container_id_to_idx.lock_or_recover(),
+            container_idx_depth: self.inner.depth.lock_or_recover(),
+            str: self.inner.str.lock_or_recover(),
+            values: self.inner.values.lock_or_recover(),
+            root_c_idx: self.inner.root_c_idx.lock_or_recover(),
+            parents: self.inner.parents.lock_or_recover(),
         };
         f(&mut op_converter)
     }
@@ -392,7 +416,8 @@ impl SharedArena {
     }
 
     pub fn can_import_snapshot(&self) -> bool {
-        self.inner.str.lock().unwrap().is_empty() && self.inner.values.lock().unwrap().is_empty()
+        self.inner.str.lock_or_recover().is_empty()
+            && self.inner.values.lock_or_recover().is_empty()
     }
 
     fn inner_convert_op(
@@ -488,12 +513,12 @@ impl SharedArena {
 
     #[inline]
     pub fn export_containers(&self) -> Vec<ContainerID> {
-        self.inner.container_idx_to_id.lock().unwrap().clone()
+        self.inner.container_idx_to_id.lock_or_recover().clone()
     }
 
     pub fn export_parents(&self) -> Vec<Option<ContainerIdx>> {
-        let parents = self.inner.parents.lock().unwrap();
-        let containers = self.inner.container_idx_to_id.lock().unwrap();
+        let parents = self.inner.parents.lock_or_recover();
+        let containers = self.inner.container_idx_to_id.lock_or_recover();
         containers
             .iter()
             .enumerate()
@@ -507,14 +532,14 @@ impl SharedArena {
 
     #[inline]
     pub fn root_containers(&self) -> Vec<ContainerIdx> {
-        self.inner.root_c_idx.lock().unwrap().clone()
+        self.inner.root_c_idx.lock_or_recover().clone()
     }
 
     pub(crate) fn get_depth(&self, container: ContainerIdx) -> Option<u16> {
         get_depth(
             container,
-            &mut self.inner.depth.lock().unwrap(),
-            &self.inner.parents.lock().unwrap(),
+            &mut self.inner.depth.lock_or_recover(),
+            &self.inner.parents.lock_or_recover(),
         )
     }
 }
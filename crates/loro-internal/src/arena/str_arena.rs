@@ -2,7 +2,7 @@ use std::ops::{Bound, RangeBounds};
 
 use append_only_bytes::{AppendOnlyBytes, BytesSlice};
 
-use crate::container::richtext::richtext_state::unicode_to_utf8_index;
+use crate::{container::richtext::richtext_state::unicode_to_utf8_index, utils::lock::Recover};
 const INDEX_INTERVAL: u32 = 128;
 
 #[derive(Default, Debug)]
@@ -12,6 +12,34 @@ pub(crate) struct StrArena {
     len: Index,
 }
 
+impl Recover for StrArena {
+    /// The bytes are only appended as whole strs, so they are always valid. But a panic
+    /// in the middle of an alloc may leave the lengths and the indexes behind or ahead
+    /// of them, so they are rebuilt from the bytes.
+    fn recover(&mut self) {
+        let bytes = match std::str::from_utf8(&self.bytes[..]) {
+            Ok(s) => s,
+            Err(e) => std::str::from_utf8(&self.bytes[..e.valid_up_to()]).unwrap(),
+        };
+        let mut len = Index::default();
+        let mut unicode_indexes = Vec::new();
+        if !bytes.is_empty() {
+            unicode_indexes.push(len);
+        }
+        for c in bytes.chars() {
+            len.bytes += c.len_utf8() as u32;
+            len.utf16 += c.len_utf16() as u32;
+            len.unicode += 1;
+            if len.bytes - unicode_indexes.last().unwrap().bytes > INDEX_INTERVAL {
+                unicode_indexes.push(len);
+            }
+        }
+
+        self.len = len;
+        self.unicode_indexes = unicode_indexes;
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 struct Index {
     bytes: u32,
@@ -153,6 +181,23 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn recover() {
+        let mut arena = StrArena::default();
+        let src = "一二34567八九零".repeat(100);
+        arena.alloc(&src);
+        // as if a panic interrupted an alloc after updating the lengths
+        arena.len.unicode += 10;
+        arena.unicode_indexes.truncate(1);
+        arena.recover();
+        assert_eq!(arena.len_unicode(), 1000);
+        assert_eq!(arena.len_utf16(), 1000);
+        assert_eq!(arena.len_bytes(), src.len());
+        assert_eq!(arena.slice_str_by_unicode(995..1000), "67八九零");
+        arena.alloc("abc");
+        assert_eq!(arena.slice_str_by_unicode(1000..1003), "abc");
+    }
+
     #[test]
     fn test() {
         let mut arena = StrArena::default();
@@ -15,13 +15,55 @@ use loro_common::{HasCounter, HasCounterSpan, PeerID};
 use num::traits::AsPrimitive;
 use rle::{HasIndex, HasLength, Mergable, RleVec, Sliceable};
 use smallvec::SmallVec;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+/// [Unix time](https://en.wikipedia.org/wiki/Unix_time) in seconds.
+///
+/// Use [TimestampExt] to convert it from/to [SystemTime].
 pub type Timestamp = i64;
 pub type Lamport = u32;
 
+/// Conversions between [Timestamp] and [SystemTime].
+///
+/// [Timestamp] is the number of seconds that have elapsed since 00:00:00 UTC
+/// on 1 January 1970. The sub-second part is truncated.
+pub trait TimestampExt: Sized {
+    /// The current system time
+    fn now() -> Self;
+    fn from_system_time(time: SystemTime) -> Self;
+    fn to_system_time(self) -> SystemTime;
+}
+
+impl TimestampExt for Timestamp {
+    #[inline]
+    fn now() -> Self {
+        get_sys_timestamp()
+    }
+
+    fn from_system_time(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as Timestamp,
+            Err(e) => -(e.duration().as_secs() as Timestamp),
+        }
+    }
+
+    fn to_system_time(self) -> SystemTime {
+        let secs = Duration::from_secs(self.unsigned_abs());
+        if self >= 0 {
+            UNIX_EPOCH + secs
+        } else {
+            UNIX_EPOCH - secs
+        }
+    }
+}
+
 /// A `Change` contains a list of [Op]s.
 ///
 /// When undo/redo we should always undo/redo a whole [Change].
+///
+/// There is no undo manager yet. Reverting only the local effect of a change needs the
+/// diff between the current version and the current version without that change, but
+/// versions can only be checked out by [Frontiers], which are causally closed.
 // PERF change slice and getting length is kinda slow I guess
 #[derive(Debug, Clone)]
 pub struct Change<O = Op> {
@@ -201,7 +243,6 @@ impl Change {
 
 #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 pub(crate) fn get_sys_timestamp() -> Timestamp {
-    use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -231,4 +272,24 @@ mod test {
         let size = std::mem::size_of::<Change>();
         println!("{}", size);
     }
+
+    #[test]
+    fn timestamp_system_time() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_500_500);
+        assert_eq!(Timestamp::from_system_time(time), 1500);
+        assert_eq!(
+            Timestamp::to_system_time(1500),
+            UNIX_EPOCH + Duration::from_secs(1500)
+        );
+        assert_eq!(
+            Timestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(10)),
+            -10
+        );
+        assert_eq!(
+            Timestamp::to_system_time(-10),
+            UNIX_EPOCH - Duration::from_secs(10)
+        );
+        let now = Timestamp::now();
+        assert_eq!(Timestamp::from_system_time(now.to_system_time()), now);
+    }
 }
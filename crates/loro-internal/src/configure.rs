@@ -1,17 +1,39 @@
 use std::{fmt::Debug, sync::Arc};
 
-use crate::Timestamp;
+use crate::{change::get_sys_timestamp, Timestamp};
 
+/// The configuration of a [crate::LoroDoc].
+///
+/// There is no garbage collection to configure: the whole history, including the
+/// deleted text, is kept so that any version can be checked out and synced.
 #[derive(Clone)]
 pub struct Configure {
+    /// Returns the current time as seconds since the Unix epoch.
+    /// See [crate::change::TimestampExt].
     pub get_time: fn() -> Timestamp,
     pub rand: Arc<dyn SecureRandomGenerator>,
+    /// Whether a local change can be merged into the previous change of the same peer.
+    ///
+    /// It only applies to the changes committed by this doc's transactions.
+    /// Imported changes are never merged, whatever the setting of either peer.
+    ///
+    /// Merging keeps the history small. Disable it if every transaction should
+    /// be kept as a separate change with its own timestamp, e.g. for an audit log.
+    pub merge_local_ops: bool,
+    /// Whether the imported updates can be applied to the state directly, skipping the
+    /// diff calculation, when they are a linear history on top of the current state.
+    ///
+    /// Enable it for docs that are edited by a single writer at a time, e.g. a replica
+    /// that only follows one device. Concurrent updates still take the normal path.
+    pub single_writer: bool,
 }
 
 impl Debug for Configure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Configure")
             .field("get_time", &self.get_time)
+            .field("merge_local_ops", &self.merge_local_ops)
+            .field("single_writer", &self.single_writer)
             .finish()
     }
 }
@@ -67,8 +89,10 @@ pub trait SecureRandomGenerator: Send + Sync {
 impl Default for Configure {
     fn default() -> Self {
         Self {
-            get_time: || 0,
+            get_time: get_sys_timestamp,
             rand: Arc::new(DefaultRandom),
+            merge_local_ops: true,
+            single_writer: false,
         }
     }
 }
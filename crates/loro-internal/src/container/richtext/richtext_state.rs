@@ -6,6 +6,7 @@ use generic_btree::{
 };
 use loro_common::LoroValue;
 use serde::{ser::SerializeStruct, Serialize};
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::{
     ops::{Add, AddAssign, Range, Sub},
@@ -37,6 +38,10 @@ use super::{
 
 pub(crate) use query::PosType;
 
+/// The text is stored in a [BTree] from `generic-btree`, which keeps its nodes in
+/// its own arena. Unlike [rle::RleTree], whose arena is chosen by
+/// [rle::rle_tree::tree_trait::RleTreeTrait::Arena], the allocator is not pluggable,
+/// so there is no bump-allocated mode for short-lived docs.
 #[derive(Clone, Debug, Default)]
 pub(crate) struct RichtextState {
     tree: BTree<RichtextTreeTrait>,
@@ -1234,6 +1239,24 @@ impl RichtextState {
         }
     }
 
+    /// Convert the entity index to event index, it's clamped to the length of the text.
+    pub(crate) fn entity_index_to_event_index(&self, entity_index: usize) -> usize {
+        if entity_index >= self.len_entity() {
+            return if cfg!(feature = "wasm") {
+                self.len_utf16()
+            } else {
+                self.len_unicode()
+            };
+        }
+
+        let cursor = self
+            .tree
+            .query::<EntityQuery>(&entity_index)
+            .unwrap()
+            .cursor;
+        self.cursor_to_event_index(cursor)
+    }
+
     /// Convert cursor position to event index:
     ///
     /// - If feature="wasm", index is utf16 index,
@@ -1453,6 +1476,39 @@ impl RichtextState {
         entity_index
     }
 
+    /// Get the text, borrowed if it's stored in a single chunk.
+    pub(crate) fn to_str_cow(&self) -> Cow<'_, str> {
+        let mut texts = self.tree.iter().filter_map(|x| match x {
+            RichtextStateChunk::Text(s) => Some(s),
+            RichtextStateChunk::Style { .. } => None,
+        });
+        match (texts.next(), texts.next()) {
+            (None, _) => Cow::Borrowed(""),
+            (Some(s), None) => Cow::Borrowed(s.as_str()),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
+
+    /// Get the char at the unicode index `pos` by querying the tree,
+    /// return None if `pos` is out of bound.
+    pub(crate) fn char_at(&self, pos: usize) -> Option<char> {
+        if pos >= self.len_unicode() {
+            return None;
+        }
+
+        let mut cursor = self.tree.query::<UnicodeQuery>(&pos)?.cursor;
+        loop {
+            // the cursor may be at the end of a chunk or at a style anchor
+            if let RichtextStateChunk::Text(s) = self.tree.get_elem(cursor.leaf)? {
+                if cursor.offset < s.rle_len() {
+                    return s.as_str().chars().nth(cursor.offset);
+                }
+            }
+
+            cursor = self.tree.next_elem(cursor)?;
+        }
+    }
+
     pub(crate) fn get_text_entity_ranges(
         &self,
         pos: usize,
@@ -7,7 +7,7 @@ use crate::VersionVector;
 use self::{crdt_rope::CrdtRope, id_to_cursor::IdToCursor};
 
 use super::{
-    fugue_span::{FugueSpan, Status},
+    fugue_span::{FugueSpan, RichtextChunkKind, Status},
     RichtextChunk,
 };
 
@@ -266,6 +266,63 @@ impl Tracker {
         }
     }
 
+    /// Get the id of the element at the given entity index in the current version.
+    ///
+    /// Unknown spans are skipped, so the result is only meaningful when the
+    /// whole history of the container has been applied to this tracker.
+    pub(crate) fn id_at(&self, mut entity_index: usize) -> Option<ID> {
+        for span in self.rope.tree().iter() {
+            if !span.status.is_activated() || span.content.kind() == RichtextChunkKind::Unknown {
+                continue;
+            }
+
+            let len = span.content.len();
+            if entity_index < len {
+                return Some(span.id.inc(entity_index as Counter));
+            }
+
+            entity_index -= len;
+        }
+
+        None
+    }
+
+    /// Get the entity index of the element with the given id in the current version,
+    /// and whether the element has been deleted.
+    ///
+    /// A deleted element gets the entity index of the next element that is not deleted.
+    /// Like [Self::id_at], unknown spans are skipped.
+    pub(crate) fn entity_index_of(&self, id: ID) -> Option<(usize, bool)> {
+        let mut entity_index = 0;
+        for span in self.rope.tree().iter() {
+            if span.content.kind() == RichtextChunkKind::Unknown {
+                continue;
+            }
+
+            let len = span.content.len();
+            let activated = span.status.is_activated();
+            if span.id.peer == id.peer
+                && span.id.counter <= id.counter
+                && id.counter < span.id.counter + len as Counter
+            {
+                if activated {
+                    return Some((
+                        entity_index + (id.counter - span.id.counter) as usize,
+                        false,
+                    ));
+                }
+
+                return Some((entity_index, true));
+            }
+
+            if activated {
+                entity_index += len;
+            }
+        }
+
+        None
+    }
+
     pub(crate) fn diff(
         &mut self,
         from: &VersionVector,
@@ -161,7 +161,7 @@ impl CrdtRope {
                 if content.origin_left == other_origin_left {
                     if other_elem.origin_right == content.origin_right {
                         // debug_log::debug_log!("Same right parent");
-                        // Same right parent
+                        // Same right parent, the tie is broken by peer id: the smaller peer is on the left
                         if other_elem.id.peer > content.id.peer {
                             // debug_log::debug_log!("Break on larger peer");
                             break;
@@ -27,6 +27,7 @@ use crate::{
     id::Counter,
     op::RichOp,
     span::{HasId, HasLamport},
+    utils::lock::{LockOrRecover, Recover},
     version::Frontiers,
     InternalString, VersionVector,
 };
@@ -49,6 +50,14 @@ pub struct DiffCalculator {
     has_all: bool,
 }
 
+impl Recover for DiffCalculator {
+    /// The calculators are only a cache, so drop them instead of trusting a
+    /// half-updated one.
+    fn recover(&mut self) {
+        *self = Self::new();
+    }
+}
+
 impl DiffCalculator {
     pub fn new() -> Self {
         Self {
@@ -315,6 +324,153 @@ impl DiffCalculator {
             .map(|x| x.1)
             .collect_vec()
     }
+
+    /// Calculate the diff of a single richtext container from the empty version to `vv`.
+    ///
+    /// Only the ops of the given container are tracked, so the other containers
+    /// don't need to be checked out.
+    pub(crate) fn calc_richtext_diff_from_empty(
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        vv: &VersionVector,
+    ) -> InternalDiff {
+        let mut calculator = Self::track_richtext_from_empty(oplog, idx, vv);
+        calculator.calculate_diff(oplog, &VersionVector::new(), vv, |_| {})
+    }
+
+    /// Get the id of the element at `entity_index` of a richtext container at version `vv`.
+    ///
+    /// The tracker of the container is cached in the [OpLog], so only the ops
+    /// that are new to the cached tracker are replayed.
+    pub(crate) fn richtext_id_at_entity_index(
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        vv: &VersionVector,
+        entity_index: usize,
+    ) -> Option<ID> {
+        let mut cache = oplog.richtext_tracker_cache.lock_or_recover();
+        cache.tracker_at(oplog, idx, vv).id_at(entity_index)
+    }
+
+    /// Get the entity index of the element with `id` in a richtext container at version `vv`,
+    /// and whether the element has been deleted.
+    ///
+    /// Like [Self::richtext_id_at_entity_index], the cached tracker of the container is used.
+    pub(crate) fn richtext_entity_index_of_id(
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        vv: &VersionVector,
+        id: ID,
+    ) -> Option<(usize, bool)> {
+        let mut cache = oplog.richtext_tracker_cache.lock_or_recover();
+        cache.tracker_at(oplog, idx, vv).entity_index_of(id)
+    }
+
+    fn track_richtext_from_empty(
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        vv: &VersionVector,
+    ) -> RichtextDiffCalculator {
+        let empty_vv = VersionVector::new();
+        let mut calculator = RichtextDiffCalculator::default();
+        calculator.start_tracking(oplog, &empty_vv);
+        Self::track_richtext_ops(&mut calculator, oplog, idx, &empty_vv, vv);
+        calculator
+    }
+
+    /// Apply the ops of the richtext container `idx` between `from` and `to` to `calculator`.
+    ///
+    /// `calculator` should have tracked all the ops of the container in `from`.
+    fn track_richtext_ops(
+        calculator: &mut RichtextDiffCalculator,
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        from: &VersionVector,
+        to: &VersionVector,
+    ) {
+        for (change, change_vv) in oplog.iter_causally(from.clone(), to.clone()) {
+            let start_counter = from.get(&change.id.peer).copied().unwrap_or(0);
+            let end_counter = to.get(&change.id.peer).copied().unwrap_or(0);
+            let mut checked_out = false;
+            for op in change.ops.iter() {
+                if op.container != idx || op.ctr_end() <= start_counter {
+                    continue;
+                }
+
+                if op.counter >= end_counter {
+                    break;
+                }
+
+                let sliced_op;
+                let op = if op.counter < start_counter || op.ctr_end() > end_counter {
+                    sliced_op = op.slice(
+                        (start_counter.max(op.counter) - op.counter) as usize,
+                        (end_counter.min(op.ctr_end()) - op.counter) as usize,
+                    );
+                    &sliced_op
+                } else {
+                    op
+                };
+
+                let vv = if checked_out {
+                    None
+                } else {
+                    checked_out = true;
+                    // The change may be partially tracked already, so the ops of
+                    // the change before this op must be included in the version
+                    let mut vv = change_vv.borrow().clone();
+                    vv.extend_to_include_end_id(ID::new(change.id.peer, op.counter));
+                    Some(vv)
+                };
+                calculator.apply_change(oplog, RichOp::new_by_change(change, op), vv.as_ref());
+            }
+        }
+
+        calculator.stop_tracking(oplog, to);
+    }
+}
+
+/// The trackers of single richtext containers, cached in the [OpLog] to resolve
+/// the ids of their elements without replaying the whole history every time.
+#[derive(Debug, Default)]
+pub(crate) struct RichtextTrackerCache {
+    /// The tracker of each container and the version whose ops it has tracked
+    trackers: FxHashMap<ContainerIdx, (VersionVector, RichtextDiffCalculator)>,
+}
+
+impl Recover for RichtextTrackerCache {
+    /// A tracker may be half updated, so all of them are dropped.
+    fn recover(&mut self) {
+        self.trackers.clear();
+    }
+}
+
+impl RichtextTrackerCache {
+    /// Get the tracker of the container `idx` checked out to `vv`.
+    ///
+    /// The ops in `vv` that the tracker hasn't seen are applied to it first.
+    fn tracker_at(
+        &mut self,
+        oplog: &OpLog,
+        idx: ContainerIdx,
+        vv: &VersionVector,
+    ) -> &mut RichtextTracker {
+        let (tracked_vv, calculator) = self.trackers.entry(idx).or_insert_with(|| {
+            let empty_vv = VersionVector::new();
+            let mut calculator = RichtextDiffCalculator::default();
+            calculator.start_tracking(oplog, &empty_vv);
+            (empty_vv, calculator)
+        });
+        if !tracked_vv.includes_vv(vv) {
+            let mut merged = tracked_vv.clone();
+            merged.merge(vv);
+            DiffCalculator::track_richtext_ops(calculator, oplog, idx, tracked_vv, &merged);
+            *tracked_vv = merged;
+        }
+
+        calculator.tracker.checkout(vv);
+        &mut calculator.tracker
+    }
 }
 
 /// DiffCalculator should track the history first before it can calculate the difference.
@@ -742,7 +898,7 @@ impl DiffCalculatorTrait for TreeDiffCalculator {
             parent: *parent,
             effected: true,
         };
-        let mut tree_cache = oplog.tree_parent_cache.lock().unwrap();
+        let mut tree_cache = oplog.tree_parent_cache.lock_or_recover();
         tree_cache.add_node(node);
     }
 
@@ -767,7 +923,7 @@ impl DiffCalculatorTrait for TreeDiffCalculator {
         let lca_frontiers = lca_vv.to_frontiers(&oplog.dag);
         debug_log::debug_log!("lca vv {:?}", lca_vv);
 
-        let mut tree_cache = oplog.tree_parent_cache.lock().unwrap();
+        let mut tree_cache = oplog.tree_parent_cache.lock_or_recover();
         let to_max_lamport = self.get_max_lamport_by_frontiers(&to_frontiers, oplog);
         let lca_min_lamport = self.get_min_lamport_by_frontiers(&lca_frontiers, oplog);
         let from_min_lamport = self.get_min_lamport_by_frontiers(&from_frontiers, oplog);
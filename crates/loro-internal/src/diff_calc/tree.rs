@@ -10,6 +10,7 @@ use loro_common::{CounterSpan, IdSpan, TreeID, ID};
 use crate::{
     change::Lamport,
     delta::{TreeDelta, TreeDeltaItem, TreeInternalDiff},
+    utils::lock::Recover,
     VersionVector,
 };
 
@@ -47,6 +48,8 @@ pub struct TreeDiffCache {
     current_version: VersionVector,
 }
 
+impl Recover for TreeDiffCache {}
+
 #[derive(Default)]
 struct Cache(FxHashMap<TreeID, BTreeSet<MoveLamportAndID>>);
 
@@ -13,10 +13,12 @@ use rle::HasLength;
 
 use crate::{oplog::OpLog, LoroError, VersionVector};
 
-use self::encode_updates::decode_oplog_updates;
+use self::encode_updates::{decode_oplog_updates, decode_updates};
 
-pub(crate) use encode_enhanced::{decode_oplog_v2, encode_oplog_v2};
-pub(crate) use encode_updates::encode_oplog_updates;
+pub(crate) use encode_enhanced::{decode_changes_v2, decode_oplog_v2, encode_oplog_v2};
+pub(crate) use encode_updates::{
+    decode_change_list, encode_change_list, encode_oplog_updates, encode_updates,
+};
 
 pub(crate) const COMPRESS_RLE_THRESHOLD: usize = 20 * 1024;
 // TODO: Test this threshold
@@ -26,6 +28,11 @@ pub(crate) const UPDATE_ENCODE_THRESHOLD: usize = 32;
 pub(crate) const UPDATE_ENCODE_THRESHOLD: usize = 16;
 pub(crate) const MAGIC_BYTES: [u8; 4] = [0x6c, 0x6f, 0x72, 0x6f];
 pub(crate) const ENCODE_SCHEMA_VERSION: u8 = 0;
+/// Set on the mode byte when a CRC32 checksum of all the preceding bytes is appended.
+/// Data exported before the checksum was introduced doesn't have it and is still accepted.
+const CHECKSUM_FLAG: u8 = 0x80;
+const HEADER_LEN: usize = 6;
+const CHECKSUM_LEN: usize = 4;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum EncodeMode {
@@ -101,15 +108,37 @@ pub(crate) fn encode_oplog(oplog: &OpLog, vv: &VersionVector, mode: EncodeMode)
     };
     ans.push(mode.to_byte());
     ans.extend(encoded);
+    append_checksum(&mut ans);
     ans
 }
 
-pub(crate) fn decode_oplog(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError> {
-    if input.len() < 6 {
-        return Err(LoroError::DecodeError("".into()));
-    }
+/// Encode the given changes in [EncodeMode::Updates] with the header,
+/// so the result can be imported like the output of [encode_oplog].
+pub(crate) fn encode_changes(changes: RemoteClientChanges) -> Vec<u8> {
+    let mut ans = Vec::from(MAGIC_BYTES);
+    ans.push(ENCODE_SCHEMA_VERSION);
+    ans.push(EncodeMode::Updates.to_byte());
+    ans.extend(encode_updates(changes));
+    append_checksum(&mut ans);
+    ans
+}
+
+/// Set the checksum flag on the header of `bytes` and append the checksum.
+pub(crate) fn append_checksum(bytes: &mut Vec<u8>) {
+    bytes[HEADER_LEN - 1] |= CHECKSUM_FLAG;
+    let checksum = crc32fast::hash(bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+}
 
-    let (magic_bytes, input) = input.split_at(4);
+/// Parse the header of the encoded data and return the body and its [EncodeMode].
+///
+/// If the header says a checksum is present, it's verified before anything is decoded,
+/// and [LoroError::Corrupted] is returned on mismatch.
+pub(crate) fn parse_encode_header(bytes: &[u8]) -> Result<(&[u8], EncodeMode), LoroError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(LoroError::DecodeError("Invalid import data".into()));
+    }
+    let (magic_bytes, input) = bytes.split_at(4);
     let magic_bytes: [u8; 4] = magic_bytes.try_into().unwrap();
     if magic_bytes != MAGIC_BYTES {
         return Err(LoroError::DecodeError("Invalid header bytes".into()));
@@ -119,8 +148,32 @@ pub(crate) fn decode_oplog(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroEr
         return Err(LoroError::DecodeError("Invalid version".into()));
     }
 
-    let mode: EncodeMode = input[0].try_into()?;
-    let decoded = &input[1..];
+    let mode_byte = input[0];
+    let mode: EncodeMode = (mode_byte & !CHECKSUM_FLAG).try_into()?;
+    if mode_byte & CHECKSUM_FLAG == 0 {
+        return Ok((&input[1..], mode));
+    }
+
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err(LoroError::Corrupted);
+    }
+    let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if crc32fast::hash(data).to_le_bytes() != checksum {
+        return Err(LoroError::Corrupted);
+    }
+    Ok((&data[HEADER_LEN..], mode))
+}
+
+/// Decode the bytes exported by [encode_oplog] into `oplog`.
+///
+/// The checksum, if present, is verified before decoding, so corrupted data
+/// returns [LoroError::Corrupted] instead of failing somewhere in the decoder.
+///
+/// The input must be complete. None of the encodings can be decoded incrementally:
+/// the columnar and postcard payloads have no change boundaries, and the compressed
+/// mode is a single deflate stream. Truncated input returns an error instead of panicking.
+pub(crate) fn decode_oplog(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError> {
+    let (decoded, mode) = parse_encode_header(input)?;
     match mode {
         EncodeMode::Updates => decode_oplog_updates(oplog, decoded),
         EncodeMode::Snapshot => unimplemented!(),
@@ -131,3 +184,29 @@ pub(crate) fn decode_oplog(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroEr
         EncodeMode::Auto => unreachable!(),
     }
 }
+
+/// Decode the changes in the bytes exported by [encode_oplog] without importing them,
+/// and pass them to `f` together with whether their lamports are known.
+///
+/// The lamports are only encoded in [EncodeMode::Updates]. The other modes
+/// calculate them from the deps on import.
+pub(crate) fn with_decoded_changes<R>(
+    input: &[u8],
+    f: impl FnOnce(&[Change<RemoteOp>], bool) -> Result<R, LoroError>,
+) -> Result<R, LoroError> {
+    let (decoded, mode) = parse_encode_header(input)?;
+    match mode {
+        EncodeMode::Updates => {
+            let changes: Vec<_> = decode_updates(decoded)?.into_values().flatten().collect();
+            f(&changes, true)
+        }
+        EncodeMode::Snapshot => Err(LoroError::DecodeError(
+            "Snapshot doesn't contain changes to check".into(),
+        )),
+        EncodeMode::RleUpdates => decode_changes_v2(decoded, |changes| f(&changes, false)),
+        EncodeMode::CompressedRleUpdates => miniz_oxide::inflate::decompress_to_vec(decoded)
+            .map_err(|_| LoroError::DecodeError("Invalid compressed data".into()))
+            .and_then(|bytes| decode_changes_v2(&bytes, |changes| f(&changes, false))),
+        EncodeMode::Auto => unreachable!(),
+    }
+}
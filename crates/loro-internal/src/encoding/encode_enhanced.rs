@@ -89,13 +89,13 @@ enum Kind {
 }
 
 impl Kind {
-    fn from_byte(byte: u8) -> Self {
+    fn from_byte(byte: u8) -> Result<Self, LoroError> {
         match byte {
-            0 => Self::Insert,
-            1 => Self::Delete,
-            2 => Self::TextAnchorStart,
-            3 => Self::TextAnchorEnd,
-            _ => panic!("invalid kind byte"),
+            0 => Ok(Self::Insert),
+            1 => Ok(Self::Delete),
+            2 => Ok(Self::TextAnchorStart),
+            3 => Ok(Self::TextAnchorEnd),
+            _ => Err(invalid_data()),
         }
     }
 
@@ -467,7 +467,22 @@ fn extract_containers(
     (root_containers, container_idx2index, normal_containers)
 }
 
+fn invalid_data() -> LoroError {
+    LoroError::DecodeError("Invalid data".into())
+}
+
 pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError> {
+    decode_changes_v2(input, |changes| import_changes_v2(oplog, changes))
+}
+
+/// Decode the changes and pass them to `f`.
+///
+/// The lamports of the changes are unknown (zero) until they are imported.
+/// The ops may borrow the decoded columns, so the changes are only available inside `f`.
+pub(crate) fn decode_changes_v2<R>(
+    input: &[u8],
+    f: impl FnOnce(Vec<Change<RemoteOp<'_>>>) -> Result<R, LoroError>,
+) -> Result<R, LoroError> {
     let encoded = iter_from_bytes::<DocEncoding>(input)
         .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
 
@@ -494,21 +509,31 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
     let mut style_key_iter = style_key.into_iter();
     let mut style_value_iter = style_values.into_iter();
     let mut style_info_iter = style_info.iter();
-    let get_container = |idx: usize| {
+    let get_peer = |idx: usize| peers.get(idx).copied().ok_or_else(invalid_data);
+    let get_tree_id = |idx: usize| -> Result<TreeID, LoroError> {
+        let encoding = tree_ids.get(idx).ok_or_else(invalid_data)?;
+        Ok(TreeID {
+            peer: get_peer(encoding.client_idx as usize)?,
+            counter: encoding.counter,
+        })
+    };
+    let get_container = |idx: usize| -> Result<ContainerID, LoroError> {
         if idx < root_containers.len() {
-            let Some(container) = root_containers.get(idx) else {
-                return None;
-            };
-            Some(ContainerID::Root {
+            let container = &root_containers[idx];
+            Ok(ContainerID::Root {
                 name: container.name.clone(),
                 container_type: container.type_,
             })
         } else {
-            let Some(container) = normal_containers.get(idx - root_containers.len()) else {
-                return None;
-            };
-            Some(ContainerID::Normal {
-                peer: peers[container.peer_idx as usize],
+            let container = normal_containers
+                .get(idx - root_containers.len())
+                .ok_or_else(invalid_data)?;
+            if !(1..=4).contains(&container.type_) {
+                return Err(invalid_data());
+            }
+
+            Ok(ContainerID::Normal {
+                peer: get_peer(container.peer_idx as usize)?,
                 counter: container.counter,
                 container_type: ContainerType::from_u8(container.type_),
             })
@@ -516,12 +541,9 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
     };
 
     let mut value_iter = values.into_iter();
-    let mut str_index = 0;
+    let mut str_index: usize = 0;
     let changes = change_encodings
         .map(|change_encoding| {
-            let counter = start_counter
-                .get_mut(change_encoding.peer_idx as usize)
-                .unwrap();
             let ChangeEncoding {
                 peer_idx,
                 timestamp,
@@ -529,11 +551,16 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                 deps_len,
                 dep_on_self,
             } = change_encoding;
+            let counter = start_counter
+                .get_mut(peer_idx as usize)
+                .ok_or_else(invalid_data)?;
 
-            let peer_id = peers[peer_idx as usize];
+            let peer_id = get_peer(peer_idx as usize)?;
             let mut ops = RleVec::<[RemoteOp; 1]>::new();
-            let mut delta = 0;
+            let mut delta: Counter = 0;
+            let mut decoded_len = 0;
             for op in op_iter.by_ref().take(op_len as usize) {
+                decoded_len += 1;
                 let OpEncoding {
                     container: container_idx,
                     prop,
@@ -541,49 +568,41 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                     kind,
                 } = op;
 
-                let Some(container_id) = get_container(container_idx) else {
-                    return Err(LoroError::DecodeError("".into()));
-                };
+                let container_id = get_container(container_idx)?;
                 let container_type = container_id.container_type();
                 let content = match container_type {
                     ContainerType::Tree => {
-                        let target_encoding = tree_ids[prop - 1];
-                        let target = TreeID {
-                            peer: peers[target_encoding.client_idx as usize],
-                            counter: target_encoding.counter,
-                        };
+                        let target = get_tree_id(prop.wrapping_sub(1))?;
                         let parent = if kind == 1 {
                             None
                         } else if insert_del_len == 0 {
                             TreeID::delete_root()
                         } else {
-                            let parent_encoding = tree_ids[insert_del_len as usize - 1];
-                            let parent = TreeID {
-                                peer: peers[parent_encoding.client_idx as usize],
-                                counter: parent_encoding.counter,
-                            };
-                            Some(parent)
+                            Some(get_tree_id((insert_del_len as usize).wrapping_sub(1))?)
                         };
                         RawOpContent::Tree(TreeOp { target, parent })
                     }
                     ContainerType::Map => {
-                        let key = keys[prop].clone();
-                        if Kind::from_byte(kind) == Kind::Delete {
+                        let key = keys.get(prop).ok_or_else(invalid_data)?.clone();
+                        if Kind::from_byte(kind)? == Kind::Delete {
                             RawOpContent::Map(MapSet { key, value: None })
                         } else {
                             RawOpContent::Map(MapSet {
                                 key,
-                                value: value_iter.next().unwrap(),
+                                value: value_iter.next().ok_or_else(invalid_data)?,
                             })
                         }
                     }
                     ContainerType::List | ContainerType::Text => {
                         let pos = prop;
-                        match Kind::from_byte(kind) {
+                        match Kind::from_byte(kind)? {
                             Kind::Insert => match container_type {
                                 ContainerType::Text => {
                                     let insert_len = insert_del_len as usize;
-                                    let s = &str[str_index..str_index + insert_len];
+                                    let s = str_index
+                                        .checked_add(insert_len)
+                                        .and_then(|end| str.get(str_index..end))
+                                        .ok_or_else(invalid_data)?;
                                     str_index += insert_len;
                                     RawOpContent::List(ListOp::Insert {
                                         slice: ListSlice::from_borrowed_str(s),
@@ -591,10 +610,14 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                                     })
                                 }
                                 ContainerType::List => {
-                                    let value = value_iter.next().flatten().unwrap();
+                                    let value = value_iter
+                                        .next()
+                                        .flatten()
+                                        .and_then(|v| v.into_list().ok())
+                                        .ok_or_else(invalid_data)?;
                                     RawOpContent::List(ListOp::Insert {
                                         slice: ListSlice::RawData(Cow::Owned(
-                                            match Arc::try_unwrap(value.into_list().unwrap()) {
+                                            match Arc::try_unwrap(value) {
                                                 Ok(v) => v,
                                                 Err(v) => v.deref().clone(),
                                             },
@@ -611,10 +634,14 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                             Kind::TextAnchorStart => RawOpContent::List(ListOp::StyleStart {
                                 start: pos as u32,
                                 end: insert_del_len as u32 + pos as u32,
-                                key: keys[style_key_iter.next().unwrap()].clone(),
-                                value: style_value_iter.next().unwrap(),
+                                key: style_key_iter
+                                    .next()
+                                    .and_then(|key| keys.get(key))
+                                    .ok_or_else(invalid_data)?
+                                    .clone(),
+                                value: style_value_iter.next().ok_or_else(invalid_data)?,
                                 info: TextStyleInfoFlag::from_byte(
-                                    *style_info_iter.next().unwrap(),
+                                    *style_info_iter.next().ok_or_else(invalid_data)?,
                                 ),
                             }),
                             Kind::TextAnchorEnd => RawOpContent::List(ListOp::StyleEnd),
@@ -623,19 +650,25 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                 };
                 let remote_op = RemoteOp {
                     container: container_id,
-                    counter: *counter + delta,
+                    counter: counter.checked_add(delta).ok_or_else(invalid_data)?,
                     content,
                 };
-                delta += remote_op.content_len() as i32;
+                delta = Counter::try_from(remote_op.content_len())
+                    .ok()
+                    .and_then(|len| delta.checked_add(len))
+                    .ok_or_else(invalid_data)?;
                 ops.push(remote_op);
             }
+            if decoded_len != op_len {
+                return Err(invalid_data());
+            }
 
             let mut deps: Frontiers = (0..deps_len)
                 .map(|_| {
-                    let raw = deps_iter.next().unwrap();
-                    ID::new(peers[raw.client_idx as usize], raw.counter)
+                    let raw = deps_iter.next().ok_or_else(invalid_data)?;
+                    Ok(ID::new(get_peer(raw.client_idx as usize)?, raw.counter))
                 })
-                .collect();
+                .collect::<Result<_, LoroError>>()?;
             if dep_on_self && *counter > 0 {
                 deps.push(ID::new(peer_id, *counter - 1));
             }
@@ -653,14 +686,19 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                 deps,
             };
 
-            *counter += delta;
+            *counter = counter.checked_add(delta).ok_or_else(invalid_data)?;
             Ok(change)
         })
-        .collect::<Result<Vec<_>, LoroError>>();
-    let changes = match changes {
-        Ok(changes) => changes,
-        Err(err) => return Err(err),
-    };
+        .collect::<Result<Vec<_>, LoroError>>()?;
+    if str_index != str.len() || op_iter.next().is_some() {
+        return Err(invalid_data());
+    }
+
+    f(changes)
+}
+
+fn import_changes_v2(oplog: &mut OpLog, changes: Vec<Change<RemoteOp>>) -> Result<(), LoroError> {
+    oplog.record_incoming(changes.iter());
     let mut pending_remote_changes = Vec::new();
     debug_log::debug_dbg!(&changes);
     let mut latest_ids = Vec::new();
@@ -720,17 +758,119 @@ pub fn decode_oplog_v2(oplog: &mut OpLog, input: &[u8]) -> Result<(), LoroError>
                 peer: change.id.peer,
                 counter: change.id.counter + change.atom_len() as Counter,
             });
-            oplog.insert_new_change(change, mark);
+            oplog.insert_new_change(change, mark, false);
         }
     });
 
     let mut vv = oplog.dag.vv.clone();
     oplog.try_apply_pending(latest_ids, &mut vv);
+    oplog.notify_op_observer();
     if !oplog.batch_importing {
         oplog.dag.refresh_frontiers();
     }
 
     oplog.import_unknown_lamport_remote_changes(pending_remote_changes)?;
-    assert_eq!(str_index, str.len());
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(changes: Vec<ChangeEncoding>, ops: Vec<OpEncoding>, str: &str) -> Vec<u8> {
+        to_vec(&DocEncoding {
+            changes,
+            ops,
+            deps: Vec::new(),
+            normal_containers: Vec::new(),
+            str: Cow::Borrowed(str),
+            style_info: Cow::Borrowed(&[]),
+            style_key: Vec::new(),
+            style_values: Vec::new(),
+            root_containers: vec![RootContainer {
+                name: "text".into(),
+                type_: ContainerType::Text,
+            }],
+            start_counter: vec![0],
+            values: Vec::new(),
+            clients: vec![1],
+            keys: Vec::new(),
+            tree_ids: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    fn insert(container: usize, len: isize) -> (Vec<ChangeEncoding>, Vec<OpEncoding>) {
+        let change = ChangeEncoding {
+            peer_idx: 0,
+            timestamp: 0,
+            op_len: 1,
+            deps_len: 0,
+            dep_on_self: false,
+        };
+        let op = OpEncoding {
+            container,
+            prop: 0,
+            kind: Kind::Insert.to_byte(),
+            insert_del_len: len,
+        };
+        (vec![change], vec![op])
+    }
+
+    fn decode(input: &[u8]) -> Result<(), LoroError> {
+        decode_changes_v2(input, |_| Ok(()))
+    }
+
+    #[test]
+    fn decode_malformed_changes() {
+        let (changes, ops) = insert(0, 3);
+        decode(&encode(changes, ops, "abc")).unwrap();
+
+        // the text is longer than the inserted content
+        let (changes, ops) = insert(0, 3);
+        assert!(matches!(
+            decode(&encode(changes, ops, "abcd")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // the text is shorter than the inserted content
+        let (changes, ops) = insert(0, 3);
+        assert!(matches!(
+            decode(&encode(changes, ops, "ab")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // the container doesn't exist
+        let (changes, ops) = insert(1, 3);
+        assert!(matches!(
+            decode(&encode(changes, ops, "abc")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // the peer doesn't exist
+        let (mut changes, ops) = insert(0, 3);
+        changes[0].peer_idx = 1;
+        assert!(matches!(
+            decode(&encode(changes, ops, "abc")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // unknown op kind
+        let (changes, mut ops) = insert(0, 3);
+        ops[0].kind = 4;
+        assert!(matches!(
+            decode(&encode(changes, ops, "abc")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // there are fewer ops than the changes claim
+        let (mut changes, ops) = insert(0, 3);
+        changes[0].op_len = 2;
+        assert!(matches!(
+            decode(&encode(changes, ops, "abc")),
+            Err(LoroError::DecodeError(_))
+        ));
+        // there are more ops than the changes claim
+        let (changes, mut ops) = insert(0, 3);
+        ops.push(ops[0].clone());
+        assert!(matches!(
+            decode(&encode(changes, ops, "abcabc")),
+            Err(LoroError::DecodeError(_))
+        ));
+    }
+}
@@ -22,6 +22,7 @@ use crate::{
     op::{InnerContent, Op},
     state::RichtextState,
     state::TreeState,
+    utils::lock::{LockOrErr, LockOrRecover},
     version::Frontiers,
     InternalString, LoroError, LoroValue,
 };
@@ -34,9 +35,14 @@ use crate::{
 };
 
 pub fn encode_app_snapshot(app: &LoroDoc) -> Vec<u8> {
-    let state = app.app_state().lock().unwrap();
-    let pre_encoded_state = encode_app_state(&state);
-    let f = encode_oplog(&app.oplog().lock().unwrap(), Some(pre_encoded_state));
+    let state = app.app_state().lock_or_recover();
+    encode_snapshot(&app.oplog().lock().unwrap(), &state)
+}
+
+/// Encode the oplog and the state, which must be at the latest version of the oplog.
+pub(crate) fn encode_snapshot(oplog: &OpLog, state: &DocState) -> Vec<u8> {
+    let pre_encoded_state = encode_app_state(state);
+    let f = encode_oplog(oplog, Some(pre_encoded_state));
     // f.diagnose_size();
     f.encode()
 }
@@ -45,16 +51,16 @@ pub fn decode_app_snapshot(app: &LoroDoc, bytes: &[u8], with_state: bool) -> Res
     assert!(app.can_reset_with_snapshot());
     let data = FinalPhase::decode(bytes)?;
     if with_state {
-        let mut app_state = app.app_state().lock().unwrap();
+        let mut app_state = app.app_state().lock_or_recover();
         let (state_arena, common) = decode_state(&mut app_state, &data)?;
         let arena = app_state.arena.clone();
         decode_oplog(
-            &mut app.oplog().lock().unwrap(),
+            &mut *app.oplog().lock_or_err()?,
             &data,
             Some((arena, state_arena, common)),
         )?;
     } else {
-        decode_oplog(&mut app.oplog().lock().unwrap(), &data, None)?;
+        decode_oplog(&mut *app.oplog().lock_or_err()?, &data, None)?;
     }
     Ok(())
 }
@@ -621,7 +627,7 @@ struct PreEncodedState<'a> {
 }
 
 fn encode_app_state(app_state: &DocState) -> PreEncodedState {
-    assert!(!app_state.is_in_txn());
+    assert!(!app_state.has_uncommitted_changes());
     let mut peers = Vec::new();
     let mut peer_lookup = FxHashMap::default();
     let mut tree_ids = Vec::new();
@@ -49,7 +49,10 @@ struct EncodedChange {
 }
 
 pub(crate) fn encode_oplog_updates(oplog: &OpLog, from: &VersionVector) -> Vec<u8> {
-    let changes = oplog.export_changes_from(from);
+    encode_updates(oplog.export_changes_from(from))
+}
+
+pub(crate) fn encode_updates(changes: RemoteClientChanges) -> Vec<u8> {
     let mut updates = Updates {
         changes: Vec::with_capacity(changes.len()),
     };
@@ -78,6 +81,31 @@ pub(super) fn decode_updates(input: &[u8]) -> Result<RemoteClientChanges<'static
     Ok(changes)
 }
 
+/// Encode each change on its own, so the changes don't need to be continuous
+/// or sorted, e.g. the pending changes of an [OpLog].
+pub(crate) fn encode_change_list<'a>(
+    changes: impl Iterator<Item = Change<RemoteOp<'a>>>,
+) -> Vec<u8> {
+    let updates = Updates {
+        changes: changes
+            .map(|change| convert_changes_to_encoded(std::iter::once(change)))
+            .collect(),
+    };
+    postcard::to_allocvec(&updates).unwrap()
+}
+
+pub(crate) fn decode_change_list(
+    input: &[u8],
+) -> Result<Vec<Change<RemoteOp<'static>>>, LoroError> {
+    let updates: Updates =
+        postcard::from_bytes(input).map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
+    Ok(updates
+        .changes
+        .into_iter()
+        .flat_map(convert_encoded_to_changes)
+        .collect())
+}
+
 fn convert_changes_to_encoded<'a, I>(mut changes: I) -> EncodedClientChanges
 where
     I: Iterator<Item = Change<RemoteOp<'a>>>,
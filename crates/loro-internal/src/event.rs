@@ -5,7 +5,7 @@ use smallvec::SmallVec;
 
 use crate::{
     container::richtext::richtext_state::RichtextStateChunk,
-    delta::{Delta, MapDelta, StyleMeta, TreeDelta, TreeDiff},
+    delta::{Delta, DeltaItem, MapDelta, StyleMeta, TreeDelta, TreeDiff, TreeExternalDiff},
     op::SliceRanges,
     utils::string_slice::StringSlice,
     InternalString, LoroValue,
@@ -28,6 +28,70 @@ pub struct ContainerDiff {
     pub diff: Diff,
 }
 
+/// A child container that is created by a [ContainerDiff], e.g. by `insert_container`.
+#[derive(Debug, Clone)]
+pub struct ContainerCreated {
+    pub id: ContainerID,
+    /// The path to the new container, like [ContainerDiff::path].
+    /// It ends with the new container and its index in the parent.
+    pub path: Vec<(ContainerID, Index)>,
+}
+
+impl ContainerDiff {
+    /// Get the child containers created by this diff.
+    ///
+    /// It lets the UI mount the new containers incrementally,
+    /// without comparing the values of the whole doc.
+    pub fn created_containers(&self) -> Vec<ContainerCreated> {
+        let mut ans = Vec::new();
+        let mut push = |id: ContainerID, index: Index| {
+            let mut path = self.path.clone();
+            path.push((id.clone(), index));
+            ans.push(ContainerCreated { id, path });
+        };
+
+        match &self.diff {
+            Diff::List(delta) => {
+                let mut index = 0;
+                for item in delta.vec.iter() {
+                    match item {
+                        DeltaItem::Retain { retain, .. } => index += retain,
+                        DeltaItem::Insert { insert, .. } => {
+                            for (i, value) in insert.iter().enumerate() {
+                                if let LoroValue::Container(id) = value {
+                                    push(id.clone(), Index::Seq(index + i));
+                                }
+                            }
+                            index += insert.len();
+                        }
+                        DeltaItem::Delete { .. } => {}
+                    }
+                }
+            }
+            Diff::NewMap(delta) => {
+                for (key, value) in delta.updated.iter() {
+                    if let Some(LoroValue::Container(id)) = &value.value {
+                        push(id.clone(), Index::Key(key.clone()));
+                    }
+                }
+            }
+            Diff::Tree(tree) => {
+                for item in tree.diff.iter() {
+                    if let TreeExternalDiff::Create = item.action {
+                        push(
+                            item.target.associated_meta_container(),
+                            Index::Node(item.target),
+                        );
+                    }
+                }
+            }
+            Diff::Text(_) => {}
+        }
+
+        ans
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DiffEvent<'a> {
     /// whether the event comes from the children of the container.
@@ -36,6 +100,26 @@ pub struct DiffEvent<'a> {
     pub doc: &'a DocDiff,
 }
 
+/// The event received by the subscribers in [crate::obs::NotifyMode::Signal].
+///
+/// It only tells which container has changed and the new version of the doc.
+#[derive(Debug, Clone)]
+pub struct SignalEvent<'a> {
+    /// whether the event comes from the children of the container.
+    pub from_children: bool,
+    pub container: &'a ContainerID,
+    pub version: &'a Frontiers,
+}
+
+/// The containers changed by a transaction or an import, without the diffs.
+///
+/// It's only recorded when all the subscribers are in [crate::obs::NotifyMode::Signal].
+#[derive(Debug, Clone)]
+pub(crate) struct DocSignal {
+    pub(crate) to: Frontiers,
+    pub(crate) containers: Vec<ContainerIdx>,
+}
+
 /// It's the exposed event type.
 /// It's exposed to the user. The user can use this to apply the diff to their local state.
 ///
@@ -110,10 +194,11 @@ impl<'a> InternalDocDiff<'a> {
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
-    use loro_common::LoroValue;
+    use loro_common::{ContainerType, LoroValue};
 
+    use super::Index;
     use crate::{ApplyDiff, LoroDoc};
 
     #[test]
@@ -130,6 +215,68 @@ mod test {
         text.insert(&mut txn, 1, "223").unwrap();
         txn.commit().unwrap();
     }
+
+    #[test]
+    fn created_containers() {
+        let loro = LoroDoc::new();
+        let created = Arc::new(Mutex::new(Vec::new()));
+        let created_cloned = created.clone();
+        loro.subscribe_root(Arc::new(move |event| {
+            created_cloned
+                .lock()
+                .unwrap()
+                .extend(event.container.created_containers());
+        }));
+        let map = loro.get_map("map");
+        let list = loro.get_list("list");
+        let tree = loro.get_tree("tree");
+        let mut txn = loro.txn().unwrap();
+        let text = map
+            .insert_container(&mut txn, "text", ContainerType::Text)
+            .unwrap();
+        list.insert(&mut txn, 0, 1.into()).unwrap();
+        let sub_list = list
+            .insert_container(&mut txn, 1, ContainerType::List)
+            .unwrap();
+        let node = tree.create(&mut txn).unwrap();
+        txn.commit().unwrap();
+
+        let mut created: Vec<_> = created
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|x| (x.id.clone(), x.path.clone()))
+            .collect();
+        created.sort_by_key(|(id, _)| id.to_string());
+        let text = text.as_text().unwrap().id();
+        let sub_list = sub_list.as_list().unwrap().id();
+        let meta = node.associated_meta_container();
+        let mut expected = vec![
+            (
+                text.clone(),
+                vec![
+                    (map.id(), Index::Key("map".into())),
+                    (text, Index::Key("text".into())),
+                ],
+            ),
+            (
+                sub_list.clone(),
+                vec![
+                    (list.id(), Index::Key("list".into())),
+                    (sub_list, Index::Seq(1)),
+                ],
+            ),
+            (
+                meta.clone(),
+                vec![
+                    (tree.id(), Index::Key("tree".into())),
+                    (meta, Index::Node(node)),
+                ],
+            ),
+        ];
+        expected.sort_by_key(|(id, _)| id.to_string());
+        assert_eq!(created, expected);
+    }
 }
 
 pub type Path = SmallVec<[Index; 4]>;
@@ -192,6 +339,8 @@ pub enum Diff {
     List(Delta<Vec<LoroValue>>),
     /// - When feature `wasm` is enabled, it should use utf16 indexes.
     /// - When feature `wasm` is disabled, it should use unicode indexes.
+    ///
+    /// The delta doesn't carry the ids of the ops that inserted the text.
     Text(Delta<StringSlice, StyleMeta>),
     NewMap(MapDelta),
     Tree(TreeDiff),
@@ -1,26 +1,33 @@
 use super::{state::DocState, txn::Transaction};
 use crate::{
+    change::Timestamp,
     container::{
         idx::ContainerIdx,
         list::list_op::{DeleteSpan, ListOp},
         richtext::TextStyleInfoFlag,
         tree::tree_op::TreeOp,
     },
-    delta::{MapValue, TreeDiffItem, TreeExternalDiff},
-    op::ListSlice,
-    state::RichtextState,
+    delta::{MapValue, StyleMeta, TreeDiffItem, TreeExternalDiff},
+    diff_calc::DiffCalculator,
+    op::{ListSlice, RawOpContent},
+    state::{ContainerState, RichtextState},
     txn::EventHint,
-    utils::utf16::count_utf16_len,
+    utils::{
+        lock::{LockOrRecover, Recover},
+        utf16::count_utf16_len,
+    },
+    InternalString, OpLog, VersionVector,
 };
 use enum_as_inner::EnumAsInner;
 use fxhash::FxHashMap;
 use loro_common::{
-    ContainerID, ContainerType, LoroError, LoroResult, LoroTreeError, LoroValue, TreeID,
+    ContainerID, ContainerType, LoroError, LoroResult, LoroTreeError, LoroValue, PeerID, TreeID, ID,
 };
 use serde::{Deserialize, Serialize};
 use smallvec::smallvec;
 use std::{
     borrow::Cow,
+    ops::Range,
     sync::{Mutex, Weak},
 };
 
@@ -40,6 +47,14 @@ pub enum TextDelta {
     },
 }
 
+/// A position in a text anchored to a char, so it keeps pointing to the same place
+/// after concurrent edits. See [TextHandler::cursor_at].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextCursor {
+    /// The id of the char right after the position, `None` for the end of the text.
+    pub id: Option<ID>,
+}
+
 #[derive(Clone)]
 pub struct TextHandler {
     txn: Weak<Mutex<Option<Transaction>>>,
@@ -66,6 +81,12 @@ impl std::fmt::Debug for MapHandler {
     }
 }
 
+/// A handler of a list container.
+///
+/// The list has no move op. Moving an item by deleting and inserting it again
+/// gives it a new identity, so concurrent moves of the same item duplicate it.
+/// Use a tree container and [TreeHandler::mov] if items need to keep their
+/// identity when they are moved concurrently.
 #[derive(Clone)]
 pub struct ListHandler {
     txn: Weak<Mutex<Option<Transaction>>>,
@@ -160,17 +181,27 @@ impl TextHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_value_by_idx(self.container_idx)
     }
 
+    /// Call `f` with the text. Unlike [Self::get_value], the text is not copied
+    /// if it's stored in a single chunk.
+    ///
+    /// The state of the doc is locked while `f` runs, so `f` must not read or edit
+    /// the doc, or it deadlocks. Use [Self::get_value] to get an owned copy instead.
+    pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+        self.with_state_mut(|state| f(&state.as_str_cow()))
+    }
+
+    /// Get the text as a list of runs like `{"insert": "Hello", "attributes": {"bold": true}}`.
+    ///
+    /// [Self::get_value] returns the plain text without the styles.
     pub fn get_richtext_value(&self) -> LoroValue {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 state.as_richtext_state_mut().unwrap().get_richtext_value()
             })
@@ -180,8 +211,7 @@ impl TextHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .arena
             .idx_to_id(self.container_idx)
             .unwrap()
@@ -191,34 +221,34 @@ impl TextHandler {
         self.len_unicode() == 0
     }
 
+    /// The length of the text in UTF-8 bytes
     pub fn len_utf8(&self) -> usize {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 state.as_richtext_state_mut().unwrap().len_utf8()
             })
     }
 
+    /// The length of the text in UTF-16 code units
     pub fn len_utf16(&self) -> usize {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 state.as_richtext_state_mut().unwrap().len_utf16()
             })
     }
 
+    /// The number of Unicode scalar values in the text, e.g. for a character counter
     pub fn len_unicode(&self) -> usize {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 state.as_richtext_state_mut().unwrap().len_unicode()
             })
@@ -230,35 +260,32 @@ impl TextHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
-                if cfg!(feature = "wasm") {
-                    state.as_richtext_state_mut().unwrap().len_utf16()
-                } else {
-                    state.as_richtext_state_mut().unwrap().len_unicode()
-                }
+                state.as_richtext_state_mut().unwrap().len_event()
             })
     }
 
+    /// Run `f` on the state while holding the doc state lock once, so several reads
+    /// inside `f` see the same version of the text.
     pub fn with_state<R>(&self, f: impl FnOnce(&RichtextState) -> R) -> R {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let state = state.as_richtext_state().unwrap();
                 f(state)
             })
     }
 
+    /// Like [TextHandler::with_state], but most reads of [RichtextState] need `&mut`
+    /// because the state may be lazily loaded and caches its lengths.
     pub fn with_state_mut<R>(&self, f: impl FnOnce(&mut RichtextState) -> R) -> R {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 let state = state.as_richtext_state_mut().unwrap();
                 f(state)
@@ -271,6 +298,150 @@ impl TextHandler {
         })
     }
 
+    /// Get the text value of this container at the given version.
+    ///
+    /// Only this container is checked out, on a temporary state built from the
+    /// [OpLog]. The live [DocState] is never touched, so it stays unchanged
+    /// even if the computation panics.
+    pub fn value_at(&self, oplog: &OpLog, vv: &VersionVector) -> LoroResult<String> {
+        if !oplog.vv().includes_vv(vv) {
+            return Err(LoroError::NotFoundError(
+                format!("Cannot find the specified version {:?}", vv).into_boxed_str(),
+            ));
+        }
+
+        let diff = DiffCalculator::calc_richtext_diff_from_empty(oplog, self.container_idx, vv);
+        let mut state = RichtextState::new(self.container_idx);
+        state.apply_diff(diff, &oplog.arena);
+        Ok(state.as_string())
+    }
+
+    /// Get the unicode index of the end of the next word at or after the unicode index `pos`.
+    ///
+    /// It skips the whitespace and punctuation after `pos` and then the
+    /// following word, like moving the cursor one word right in an editor.
+    /// A word is a run of alphanumeric chars or `_`. If there is no word after
+    /// `pos`, the length of the text is returned.
+    pub fn next_word_boundary(&self, pos: usize) -> usize {
+        self.with_state(|state| state.next_word_boundary(pos).0)
+    }
+
+    /// Like [Self::next_word_boundary], but returns the [ID] of the char right
+    /// before the boundary, i.e. the last char of the word.
+    ///
+    /// Unlike an index, the id keeps pointing to the same content after
+    /// concurrent edits, so it can be used to store id-anchored selections.
+    /// Returns `None` if the text before the boundary is empty.
+    ///
+    /// The chars of the state are scanned and the id is resolved by the tracker cached
+    /// in the [OpLog], both under the same locks, so it also works when the doc is detached.
+    /// The pending transaction should be committed first.
+    pub fn next_word_boundary_id(&self, oplog: &Mutex<OpLog>, pos: usize) -> Option<ID> {
+        self.with_state_and_oplog(oplog, |state, oplog, vv| {
+            let (_, entity_index) = state.next_word_boundary(pos);
+            DiffCalculator::richtext_id_at_entity_index(
+                oplog,
+                self.container_idx,
+                vv,
+                entity_index?,
+            )
+        })
+    }
+
+    /// Lock the state and then the [OpLog], in the same order as [Transaction::new],
+    /// and call `f` with the state of this text, the oplog and the version of the state.
+    ///
+    /// The version is behind the [OpLog] when the doc is detached.
+    fn with_state_and_oplog<R>(
+        &self,
+        oplog: &Mutex<OpLog>,
+        f: impl FnOnce(&mut RichtextState, &OpLog, &VersionVector) -> R,
+    ) -> R {
+        let state = self.state.upgrade().unwrap();
+        let mut state = state.lock_or_recover();
+        let oplog = oplog.lock().unwrap();
+        let vv = oplog.dag.frontiers_to_vv(&state.frontiers).unwrap();
+        state.with_state_mut(self.container_idx, |state| {
+            f(state.as_richtext_state_mut().unwrap(), &oplog, &vv)
+        })
+    }
+
+    /// Get the id of the char at the Event Index `pos` of `state`, which is at version `vv`.
+    ///
+    /// Return `None` if `pos` is the length of the text.
+    fn id_at_event_index(
+        &self,
+        state: &mut RichtextState,
+        oplog: &OpLog,
+        vv: &VersionVector,
+        pos: usize,
+    ) -> LoroResult<Option<ID>> {
+        let len = state.len_event();
+        if pos > len {
+            return Err(LoroError::OutOfBound { pos, len });
+        }
+
+        if pos == len {
+            return Ok(None);
+        }
+
+        let entity_index =
+            state.get_text_entity_ranges_in_event_index_range(pos, 1)[0].entity_start;
+        Ok(DiffCalculator::richtext_id_at_entity_index(
+            oplog,
+            self.container_idx,
+            vv,
+            entity_index,
+        ))
+    }
+
+    /// Get a cursor anchored to the char at the Event Index `pos`.
+    /// If `pos` is the length of the text, the cursor stays at the end of the text.
+    ///
+    /// The ids are resolved by a tracker of this container that is cached in the
+    /// [OpLog], so only the ops that are new to the tracker are replayed.
+    /// The pending transaction should be committed first.
+    pub fn cursor_at(&self, oplog: &Mutex<OpLog>, pos: usize) -> LoroResult<TextCursor> {
+        self.with_state_and_oplog(oplog, |state, oplog, vv| {
+            let id = self.id_at_event_index(state, oplog, vv, pos)?;
+            Ok(TextCursor { id })
+        })
+    }
+
+    /// Get the current Event Index of the cursor, and whether its char has been deleted.
+    ///
+    /// If the char has been deleted, the position of the next char that is still alive
+    /// is returned. Return `None` if the char is unknown to the version of the state,
+    /// e.g. it's not imported yet or it's inserted after the checked out version.
+    pub fn resolve_cursor(
+        &self,
+        oplog: &Mutex<OpLog>,
+        cursor: &TextCursor,
+    ) -> Option<(usize, bool)> {
+        self.with_state_and_oplog(oplog, |state, oplog, vv| {
+            let Some(id) = cursor.id else {
+                return Some((state.len_event(), false));
+            };
+
+            let (entity_index, deleted) =
+                DiffCalculator::richtext_entity_index_of_id(oplog, self.container_idx, vv, id)?;
+            Some((state.entity_index_to_event_index(entity_index), deleted))
+        })
+    }
+
+    /// Get the peer, the change timestamp and the op id that inserted the char at the
+    /// Event Index `pos`, e.g. for an "edited by / at" tooltip.
+    ///
+    /// Return `None` if `pos` is out of bound. Like [Self::cursor_at], the id is resolved
+    /// by the cached tracker, so the pending transaction should be committed first.
+    pub fn blame(&self, oplog: &Mutex<OpLog>, pos: usize) -> Option<(PeerID, Timestamp, ID)> {
+        self.with_state_and_oplog(oplog, |state, oplog, vv| {
+            let id = self.id_at_event_index(state, oplog, vv, pos).ok()??;
+            let change = oplog.get_change_at(id)?;
+            Some((id.peer, change.timestamp, id))
+        })
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -285,6 +456,23 @@ impl TextHandler {
     ///
     /// - if feature="wasm", pos is a UTF-16 index
     /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// Concurrent insertions at the same position are ordered by peer id,
+    /// the one from the smaller peer is on the left.
+    ///
+    /// The whole `s` is inserted as a single op. To load a large initial text,
+    /// insert it at once instead of appending it piece by piece.
+    ///
+    /// There's no way to fill a text without an op: the edits of other peers refer to
+    /// the chars by the ids of the ops that inserted them, so content without ops could
+    /// neither be synced nor edited consistently.
+    ///
+    /// `s` is always copied into the append-only text arena of the doc, which stores
+    /// all the text contiguously, so an owned `String` couldn't be taken over without
+    /// a copy either.
+    ///
+    /// Return [LoroError::OutOfBound] without changing the text if `pos` is greater
+    /// than the length, so it's safe to call with untrusted positions.
     pub fn insert(&self, txn: &mut Transaction, pos: usize, s: &str) -> LoroResult<()> {
         if s.is_empty() {
             return Ok(());
@@ -297,42 +485,145 @@ impl TextHandler {
             });
         }
 
-        let (entity_index, styles) = self
-            .state
+        let (entity_index, styles) = self.entity_index_and_styles_for_insert(pos);
+        let (content, event) = text_insert_op(s, pos, entity_index, styles);
+        txn.apply_local_op(self.container_idx, content, event, &self.state)
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_many_(&self, edits: &[(usize, &str)]) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.insert_many(txn, edits))
+    }
+
+    /// Apply the inserts in order in the same transaction, so they are packed into a single change.
+    ///
+    /// Each position is an Event Index in the text after the previous inserts are applied.
+    /// The positions are resolved and the ops are applied under a single lock of the state.
+    pub fn insert_many(&self, txn: &mut Transaction, edits: &[(usize, &str)]) -> LoroResult<()> {
+        let mut edits = edits.iter().filter(|(_, s)| !s.is_empty());
+        txn.apply_local_ops(
+            self.container_idx,
+            |state| {
+                let Some(&(pos, s)) = edits.next() else {
+                    return Ok(None);
+                };
+
+                let state = state.as_richtext_state_mut().unwrap();
+                let len = state.len_event();
+                if pos > len {
+                    return Err(LoroError::OutOfBound { pos, len });
+                }
+
+                let entity_index = state.get_entity_index_for_text_insert(pos);
+                let styles = state.get_styles_at_entity_index(entity_index);
+                Ok(Some(text_insert_op(s, pos, entity_index, styles)))
+            },
+            &self.state,
+        )
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn replace_(&self, pos: usize, len: usize, s: &str) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.replace(txn, pos, len, s))
+    }
+
+    /// Replace `len` chars at `pos` with `s`.
+    ///
+    /// The delete and the insert are in the same transaction, so subscribers
+    /// receive a single event whose delta contains both of them.
+    ///
+    /// `pos` and `len` are Event Indexes.
+    pub fn replace(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        len: usize,
+        s: &str,
+    ) -> LoroResult<()> {
+        self.delete(txn, pos, len)?;
+        self.insert(txn, pos, s)
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_at_line_col_(&self, line: usize, col: usize, s: &str) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.insert_at_line_col(txn, line, col, s))
+    }
+
+    /// Insert `s` at the 0-based `line` and `col`, see [Self::line_col_to_pos].
+    pub fn insert_at_line_col(
+        &self,
+        txn: &mut Transaction,
+        line: usize,
+        col: usize,
+        s: &str,
+    ) -> LoroResult<()> {
+        let pos = self.line_col_to_pos(line, col)?;
+        self.insert(txn, pos, s)
+    }
+
+    /// Convert the 0-based `line` and `col` to an Event Index.
+    ///
+    /// Lines are separated by `\n`, and a `\r` before it is not part of the line.
+    /// `col` is a Unicode index within the line and may be the length of the line.
+    /// Return [LoroError::OutOfBound] if the line or the column doesn't exist.
+    pub fn line_col_to_pos(&self, line: usize, col: usize) -> LoroResult<usize> {
+        self.with_state(|state| state.line_col_to_event_index(line, col))
+    }
+
+    /// Insert the chars at `pos`. Empty chars are a no-op.
+    ///
+    /// The chars are collected before any lock of the doc is taken, so the
+    /// iterator may read the doc.
+    ///
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn insert_chars(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        chars: impl Iterator<Item = char>,
+    ) -> LoroResult<()> {
+        let mut chars = chars.peekable();
+        if chars.peek().is_none() {
+            return Ok(());
+        }
+
+        if pos > self.len_event() {
+            return Err(LoroError::OutOfBound {
+                pos,
+                len: self.len_event(),
+            });
+        }
+
+        let (entity_index, styles) = self.entity_index_and_styles_for_insert(pos);
+        txn.insert_text_chars(
+            self.container_idx,
+            entity_index,
+            chars,
+            pos,
+            styles,
+            &self.state,
+        )
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_chars_(&self, pos: usize, chars: impl Iterator<Item = char>) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.insert_chars(txn, pos, chars))
+    }
+
+    fn entity_index_and_styles_for_insert(&self, pos: usize) -> (usize, StyleMeta) {
+        self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 let richtext_state = &mut state.as_richtext_state_mut().unwrap();
                 let pos = richtext_state.get_entity_index_for_text_insert(pos);
                 let styles = richtext_state.get_styles_at_entity_index(pos);
                 (pos, styles)
-            });
-
-        let unicode_len = s.chars().count();
-        let event_len = if cfg!(feature = "wasm") {
-            count_utf16_len(s.as_bytes())
-        } else {
-            unicode_len
-        };
-        txn.apply_local_op(
-            self.container_idx,
-            crate::op::RawOpContent::List(crate::container::list::list_op::ListOp::Insert {
-                slice: ListSlice::RawStr {
-                    str: Cow::Borrowed(s),
-                    unicode_len,
-                },
-                pos: entity_index,
-            }),
-            EventHint::InsertText {
-                pos: pos as u32,
-                styles,
-                unicode_len: unicode_len as u32,
-                event_len: event_len as u32,
-            },
-            &self.state,
-        )
+            })
     }
 
     /// `pos` is a Event Index:
@@ -349,32 +640,37 @@ impl TextHandler {
     ///
     /// - if feature="wasm", pos is a UTF-16 index
     /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// Return [LoroError::OutOfBound] without changing the text if `pos + len` is
+    /// greater than the length.
     pub fn delete(&self, txn: &mut Transaction, pos: usize, len: usize) -> LoroResult<()> {
         if len == 0 {
             return Ok(());
         }
 
-        if pos + len > self.len_event() {
-            return Err(LoroError::OutOfBound {
-                pos: pos + len,
-                len: self.len_event(),
-            });
-        }
+        let end = match pos.checked_add(len) {
+            Some(end) if end <= self.len_event() => end,
+            _ => {
+                return Err(LoroError::OutOfBound {
+                    pos: pos.saturating_add(len),
+                    len: self.len_event(),
+                })
+            }
+        };
 
         debug_log::group!("delete pos={} len={}", pos, len);
         let ranges = self
             .state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 let richtext_state = state.as_richtext_state_mut().unwrap();
                 richtext_state.get_text_entity_ranges_in_event_index_range(pos, len)
             });
 
         debug_assert_eq!(ranges.iter().map(|x| x.event_len).sum::<usize>(), len);
-        let mut event_end = (pos + len) as isize;
+        let mut event_end = end as isize;
         for range in ranges.iter().rev() {
             let event_start = event_end - range.event_len as isize;
             txn.apply_local_op(
@@ -399,6 +695,176 @@ impl TextHandler {
         Ok(())
     }
 
+    /// This method requires auto_commit to be enabled.
+    pub fn delete_ranges_(&self, ranges: &[(usize, usize)]) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.delete_ranges(txn, ranges))
+    }
+
+    /// Delete several `(pos, len)` ranges in the same transaction.
+    ///
+    /// The positions are Event Indexes in the text before any of the deletions.
+    /// Ranges past the end are rejected with [LoroError::OutOfBound], overlapping ranges
+    /// with [LoroError::ArgErr], and nothing is deleted if any range is invalid.
+    pub fn delete_ranges(
+        &self,
+        txn: &mut Transaction,
+        ranges: &[(usize, usize)],
+    ) -> LoroResult<()> {
+        let mut ranges: Vec<_> = ranges.iter().filter(|(_, len)| *len > 0).copied().collect();
+        let len_event = self.len_event();
+        for &(pos, len) in ranges.iter() {
+            if !matches!(pos.checked_add(len), Some(end) if end <= len_event) {
+                return Err(LoroError::OutOfBound {
+                    pos: pos.saturating_add(len),
+                    len: len_event,
+                });
+            }
+        }
+
+        ranges.sort_unstable();
+        for w in ranges.windows(2) {
+            if w[0].0 + w[0].1 > w[1].0 {
+                return Err(LoroError::ArgErr(
+                    format!("Overlapping ranges {:?} and {:?}", w[0], w[1]).into_boxed_str(),
+                ));
+            }
+        }
+
+        // delete from the end so the positions of the remaining ranges stay valid
+        for &(pos, len) in ranges.iter().rev() {
+            self.delete(txn, pos, len)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the text in `start..end` without building the whole string.
+    ///
+    /// `start` and `end` are Event Indexes, `end` is clamped to the length of the text:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// Return [LoroError::ArgErr] if feature="wasm" and `start` or `end` is in the
+    /// middle of a surrogate pair.
+    pub fn slice(&self, start: usize, end: usize) -> LoroResult<String> {
+        let end = end.min(self.len_event());
+        if start >= end {
+            return Ok(String::new());
+        }
+
+        self.with_state(|state| {
+            if cfg!(feature = "wasm") {
+                let range = state.checked_utf16_range_to_unicode_range(start..end)?;
+                Ok(state.slice(range.start, range.end))
+            } else {
+                Ok(state.slice(start, end))
+            }
+        })
+    }
+
+    /// Find the first occurrence of `needle` at or after `from`.
+    ///
+    /// Both `from` and the returned position are Event Indexes. An empty `needle`
+    /// matches at `from`. Return None if there is no match or `from` is out of bound.
+    pub fn find(&self, needle: &str, from: usize) -> Option<usize> {
+        let mut ans = None;
+        self.with_state(|state| {
+            state.find(needle, from, |pos| {
+                ans = Some(pos);
+                false
+            })
+        });
+        ans
+    }
+
+    /// Find all the non-overlapping occurrences of `needle`, in Event Indexes.
+    ///
+    /// An empty `needle` matches nothing.
+    pub fn find_all(&self, needle: &str) -> Vec<usize> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ans = Vec::new();
+        self.with_state(|state| {
+            state.find(needle, 0, |pos| {
+                ans.push(pos);
+                true
+            })
+        });
+        ans
+    }
+
+    /// Get the char at the Unicode index `pos` without building the whole string.
+    ///
+    /// Return None if `pos` is out of bound.
+    pub fn char_at(&self, pos: usize) -> Option<char> {
+        self.with_state_mut(|state| state.char_at(pos))
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_utf16_(&self, utf16_pos: usize, s: &str) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.insert_utf16(txn, utf16_pos, s))
+    }
+
+    /// Insert `s` at the UTF-16 index `utf16_pos`, regardless of the `wasm` feature.
+    ///
+    /// # Panic
+    ///
+    /// Panic if `utf16_pos` is in the middle of a surrogate pair.
+    pub fn insert_utf16(&self, txn: &mut Transaction, utf16_pos: usize, s: &str) -> LoroResult<()> {
+        let pos = self.utf16_to_event_index(utf16_pos)?;
+        self.insert(txn, pos, s)
+    }
+
+    /// This method requires auto_commit to be enabled.
+    pub fn delete_utf16_(&self, utf16_pos: usize, utf16_len: usize) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| {
+            self.delete_utf16(txn, utf16_pos, utf16_len)
+        })
+    }
+
+    /// Delete `utf16_len` UTF-16 code units from the UTF-16 index `utf16_pos`,
+    /// regardless of the `wasm` feature.
+    ///
+    /// # Panic
+    ///
+    /// Panic if either end of the range is in the middle of a surrogate pair.
+    pub fn delete_utf16(
+        &self,
+        txn: &mut Transaction,
+        utf16_pos: usize,
+        utf16_len: usize,
+    ) -> LoroResult<()> {
+        let Some(utf16_end) = utf16_pos.checked_add(utf16_len) else {
+            return Err(LoroError::OutOfBound {
+                pos: utf16_pos.saturating_add(utf16_len),
+                len: self.len_utf16(),
+            });
+        };
+        let range = self.utf16_range_to_event_range(utf16_pos..utf16_end)?;
+        self.delete(txn, range.start, range.len())
+    }
+
+    fn utf16_to_event_index(&self, utf16_index: usize) -> LoroResult<usize> {
+        self.utf16_range_to_event_range(utf16_index..utf16_index)
+            .map(|range| range.start)
+    }
+
+    fn utf16_range_to_event_range(&self, utf16_range: Range<usize>) -> LoroResult<Range<usize>> {
+        let unicode_range =
+            self.with_state(|state| state.utf16_range_to_unicode_range(utf16_range.clone()));
+        match unicode_range {
+            Some(_) if cfg!(feature = "wasm") => Ok(utf16_range),
+            Some(unicode_range) => Ok(unicode_range),
+            None => Err(LoroError::OutOfBound {
+                pos: utf16_range.end,
+                len: self.len_utf16(),
+            }),
+        }
+    }
+
     /// `start` and `end` are [Event Index]s:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -422,6 +888,14 @@ impl TextHandler {
     ///
     /// - if feature="wasm", pos is a UTF-16 index
     /// - if feature!="wasm", pos is a Unicode index
+    ///
+    /// The style is anchored to the chars around the range, so it follows the text
+    /// through concurrent insertions and deletions. `flag` decides whether the text
+    /// inserted at the edges inherits the style. To remove a style, mark the range
+    /// with [LoroValue::Null] and `flag.to_delete()`.
+    ///
+    /// Where styles with the same key overlap, the one with the greater
+    /// `(lamport, peer)` wins, so concurrent bold and unbold converge on every peer.
     pub fn mark(
         &self,
         txn: &mut Transaction,
@@ -446,8 +920,7 @@ impl TextHandler {
             .state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state_mut(self.container_idx, |state| {
                 (
                     state
@@ -542,6 +1015,36 @@ impl TextHandler {
     }
 }
 
+/// Create the op that inserts `s` at the Event Index `pos`, whose entity index is `entity_index`.
+fn text_insert_op(
+    s: &str,
+    pos: usize,
+    entity_index: usize,
+    styles: StyleMeta,
+) -> (RawOpContent<'_>, EventHint) {
+    let unicode_len = s.chars().count();
+    let event_len = if cfg!(feature = "wasm") {
+        count_utf16_len(s.as_bytes())
+    } else {
+        unicode_len
+    };
+    (
+        RawOpContent::List(ListOp::Insert {
+            slice: ListSlice::RawStr {
+                str: Cow::Borrowed(s),
+                unicode_len,
+            },
+            pos: entity_index,
+        }),
+        EventHint::InsertText {
+            pos: pos as u32,
+            styles,
+            unicode_len: unicode_len as u32,
+            event_len: event_len as u32,
+        },
+    )
+}
+
 fn event_len(s: &str) -> usize {
     if cfg!(feature = "wasm") {
         count_utf16_len(s.as_bytes())
@@ -568,6 +1071,8 @@ impl ListHandler {
         with_txn(&self.txn, |txn| self.insert(txn, pos, v))
     }
 
+    /// Concurrent insertions at the same position are ordered by peer id,
+    /// the one from the smaller peer is on the left.
     pub fn insert(&self, txn: &mut Transaction, pos: usize, v: LoroValue) -> LoroResult<()> {
         if pos > self.len() {
             return Err(LoroError::OutOfBound {
@@ -683,7 +1188,7 @@ impl ListHandler {
 
     pub fn get_child_handler(&self, index: usize) -> Handler {
         let mutex = &self.state.upgrade().unwrap();
-        let state = mutex.lock().unwrap();
+        let state = mutex.lock_or_recover();
         let container_id = state.with_state(self.container_idx, |state| {
             state
                 .as_list_state()
@@ -699,12 +1204,12 @@ impl ListHandler {
         Handler::new(self.txn.clone(), idx, self.state.clone())
     }
 
+    /// The number of elements, read from the list state without building its value.
     pub fn len(&self) -> usize {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 state.as_list_state().as_ref().unwrap().len()
             })
@@ -718,8 +1223,7 @@ impl ListHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_value_by_idx(self.container_idx)
     }
 
@@ -727,8 +1231,7 @@ impl ListHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_container_deep_value(self.container_idx)
     }
 
@@ -736,8 +1239,7 @@ impl ListHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_container_deep_value_with_id(self.container_idx, None)
     }
 
@@ -745,19 +1247,21 @@ impl ListHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .arena
             .idx_to_id(self.container_idx)
             .unwrap()
     }
 
+    /// Get the value at `index` without building the value of the whole list.
+    ///
+    /// A nested container is returned as [LoroValue::Container], use [Self::get_]
+    /// to get its handler. Return None if `index` is out of bound.
     pub fn get(&self, index: usize) -> Option<LoroValue> {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_list_state().unwrap();
                 a.get(index).cloned()
@@ -767,7 +1271,7 @@ impl ListHandler {
     /// Get value at given index, if it's a container, return a handler to the container
     pub fn get_(&self, index: usize) -> Option<ValueOrContainer> {
         let mutex = &self.state.upgrade().unwrap();
-        let doc_state = &mutex.lock().unwrap();
+        let doc_state = &mutex.lock_or_recover();
         doc_state.with_state(self.container_idx, |state| {
             let a = state.as_list_state().unwrap();
             match a.get(index) {
@@ -795,8 +1299,7 @@ impl ListHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_list_state().unwrap();
                 for v in a.iter() {
@@ -849,16 +1352,48 @@ impl MapHandler {
         )
     }
 
-    pub fn insert_container_(&self, key: &str, c_type: ContainerType) -> LoroResult<Handler> {
-        with_txn(&self.txn, |txn| self.insert_container(txn, key, c_type))
-    }
-
-    pub fn insert_container(
+    /// This method requires auto_commit to be enabled.
+    pub fn compare_and_set_(
         &self,
-        txn: &mut Transaction,
         key: &str,
-        c_type: ContainerType,
-    ) -> LoroResult<Handler> {
+        expected: Option<LoroValue>,
+        value: LoroValue,
+    ) -> LoroResult<bool> {
+        with_txn(&self.txn, |txn| {
+            self.compare_and_set(txn, key, expected, value)
+        })
+    }
+
+    /// Set `key` to `value` only if its current value equals `expected`,
+    /// `None` means the key is absent. Return whether the value is set.
+    ///
+    /// This only guards against local races. Concurrent remote sets of the same key
+    /// are still merged by the last-writer-wins rule after they are imported.
+    pub fn compare_and_set(
+        &self,
+        txn: &mut Transaction,
+        key: &str,
+        expected: Option<LoroValue>,
+        value: LoroValue,
+    ) -> LoroResult<bool> {
+        if self.get(key) != expected {
+            return Ok(false);
+        }
+
+        self.insert(txn, key, value)?;
+        Ok(true)
+    }
+
+    pub fn insert_container_(&self, key: &str, c_type: ContainerType) -> LoroResult<Handler> {
+        with_txn(&self.txn, |txn| self.insert_container(txn, key, c_type))
+    }
+
+    pub fn insert_container(
+        &self,
+        txn: &mut Transaction,
+        key: &str,
+        c_type: ContainerType,
+    ) -> LoroResult<Handler> {
         let id = txn.next_id();
         let container_id = ContainerID::new_normal(id, c_type);
         let child_idx = txn.arena.register_container(&container_id);
@@ -902,6 +1437,72 @@ impl MapHandler {
         )
     }
 
+    pub fn insert_all_<K: AsRef<str>>(
+        &self,
+        entries: impl IntoIterator<Item = (K, LoroValue)>,
+    ) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.insert_all(txn, entries))
+    }
+
+    /// Insert all the entries in one transaction, so they are packed into a single
+    /// change and emitted as a single map diff.
+    pub fn insert_all<K: AsRef<str>>(
+        &self,
+        txn: &mut Transaction,
+        entries: impl IntoIterator<Item = (K, LoroValue)>,
+    ) -> LoroResult<()> {
+        for (key, value) in entries {
+            self.insert(txn, key.as_ref(), value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_all_<K: AsRef<str>>(&self, keys: impl IntoIterator<Item = K>) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.remove_all(txn, keys))
+    }
+
+    /// Delete all the keys in one transaction, see [MapHandler::insert_all]
+    pub fn remove_all<K: AsRef<str>>(
+        &self,
+        txn: &mut Transaction,
+        keys: impl IntoIterator<Item = K>,
+    ) -> LoroResult<()> {
+        for key in keys {
+            self.delete(txn, key.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn sync_to_(&self, desired: &FxHashMap<String, LoroValue>) -> LoroResult<()> {
+        with_txn(&self.txn, |txn| self.sync_to(txn, desired))
+    }
+
+    /// Make the map equal to `desired` with the minimal set of inserts and deletes.
+    ///
+    /// The keys whose values are unchanged are skipped, so they don't create ops or events.
+    pub fn sync_to(
+        &self,
+        txn: &mut Transaction,
+        desired: &FxHashMap<String, LoroValue>,
+    ) -> LoroResult<()> {
+        let current = self.get_value().into_map().unwrap();
+        for key in current.keys() {
+            if !desired.contains_key(key) {
+                self.delete(txn, key)?;
+            }
+        }
+
+        for (key, value) in desired.iter() {
+            if current.get(key) != Some(value) {
+                self.insert(txn, key, value.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn for_each<I>(&self, mut f: I)
     where
         I: FnMut(&str, &MapValue),
@@ -909,8 +1510,7 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_map_state().unwrap();
                 for (k, v) in a.iter() {
@@ -923,14 +1523,13 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_value_by_idx(self.container_idx)
     }
 
     pub fn get_child_handler(&self, key: &str) -> Handler {
         let mutex = &self.state.upgrade().unwrap();
-        let state = mutex.lock().unwrap();
+        let state = mutex.lock_or_recover();
         let container_id = state.with_state(self.container_idx, |state| {
             state
                 .as_map_state()
@@ -950,8 +1549,7 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_container_deep_value(self.container_idx)
     }
 
@@ -959,8 +1557,7 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_container_deep_value_with_id(self.container_idx, None)
     }
 
@@ -968,18 +1565,46 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_map_state().unwrap();
                 a.get(key).cloned()
             })
     }
 
+    /// Get the keys of the map. Deleted keys are skipped.
+    pub fn keys(&self) -> Vec<InternalString> {
+        self.state
+            .upgrade()
+            .unwrap()
+            .lock_or_recover()
+            .with_state(self.container_idx, |state| {
+                let a = state.as_map_state().unwrap();
+                a.iter()
+                    .filter(|(_, v)| v.value.is_some())
+                    .map(|(k, _)| k.clone())
+                    .collect()
+            })
+    }
+
+    /// Get the key-value pairs of the map. Deleted keys are skipped.
+    pub fn entries(&self) -> Vec<(InternalString, LoroValue)> {
+        self.state
+            .upgrade()
+            .unwrap()
+            .lock_or_recover()
+            .with_state(self.container_idx, |state| {
+                let a = state.as_map_state().unwrap();
+                a.iter()
+                    .filter_map(|(k, v)| v.value.as_ref().map(|v| (k.clone(), v.clone())))
+                    .collect()
+            })
+    }
+
     /// Get the value at given key, if value is a container, return a handler to the container
     pub fn get_(&self, key: &str) -> Option<ValueOrContainer> {
         let mutex = &self.state.upgrade().unwrap();
-        let doc_state = mutex.lock().unwrap();
+        let doc_state = mutex.lock_or_recover();
         doc_state.with_state(self.container_idx, |state| {
             let a = state.as_map_state().unwrap();
             let value = a.get(key);
@@ -1002,8 +1627,7 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .arena
             .idx_to_id(self.container_idx)
             .unwrap()
@@ -1013,8 +1637,7 @@ impl MapHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 state.as_map_state().as_ref().unwrap().len()
             })
@@ -1159,8 +1782,7 @@ impl TreeHandler {
             .state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .arena
             .register_container(&map_container_id);
         let map = MapHandler::new(self.txn.clone(), idx, self.state.clone());
@@ -1171,8 +1793,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_tree_state().unwrap();
                 a.parent(target)
@@ -1183,8 +1804,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .arena
             .idx_to_id(self.container_idx)
             .unwrap()
@@ -1194,8 +1814,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_tree_state().unwrap();
                 a.contains(target)
@@ -1206,8 +1825,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_value_by_idx(self.container_idx)
     }
 
@@ -1215,8 +1833,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .get_container_deep_value(self.container_idx)
     }
 
@@ -1224,8 +1841,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_tree_state().unwrap();
                 a.nodes()
@@ -1237,8 +1853,7 @@ impl TreeHandler {
         self.state
             .upgrade()
             .unwrap()
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .with_state(self.container_idx, |state| {
                 let a = state.as_tree_state().unwrap();
                 a.max_counter()
@@ -1252,7 +1867,11 @@ fn with_txn<R>(
     f: impl FnOnce(&mut Transaction) -> LoroResult<R>,
 ) -> LoroResult<R> {
     let mutex = &txn.upgrade().unwrap();
-    let mut txn = mutex.try_lock().unwrap();
+    let mut txn = mutex.try_lock_or_recover();
+    if txn.as_ref().is_some_and(Transaction::is_rolled_back) {
+        txn.recover();
+    }
+
     match &mut *txn {
         Some(t) => f(t),
         None => Err(LoroError::AutoCommitNotStarted),
@@ -1267,7 +1886,7 @@ mod test {
     use crate::loro::LoroDoc;
     use crate::version::Frontiers;
     use crate::{fx_map, ToJson};
-    use loro_common::ID;
+    use loro_common::{LoroError, ID};
     use serde_json::json;
 
     use super::TextDelta;
@@ -1348,6 +1967,642 @@ mod test {
         assert_eq!(&**text.get_value().as_string().unwrap(), "hello w");
     }
 
+    #[test]
+    fn text_value_at() {
+        let loro = LoroDoc::new();
+        loro.set_peer_id(1).unwrap();
+        let text = loro.get_text("text");
+        let map = loro.get_map("map");
+        loro.with_txn(|txn| text.insert(txn, 0, "hello")).unwrap();
+        let vv = loro.oplog_vv();
+        loro.with_txn(|txn| {
+            map.insert(txn, "key", 1.into())?;
+            text.insert(txn, 5, " world")?;
+            text.delete(txn, 0, 1)
+        })
+        .unwrap();
+
+        let oplog = loro.oplog().lock().unwrap();
+        assert_eq!(text.value_at(&oplog, &vv).unwrap(), "hello");
+        assert_eq!(text.value_at(&oplog, oplog.vv()).unwrap(), "ello world");
+        assert_eq!(text.value_at(&oplog, &Default::default()).unwrap(), "");
+        drop(oplog);
+        assert_eq!(&**text.get_value().as_string().unwrap(), "ello world");
+    }
+
+    #[test]
+    fn text_next_word_boundary() {
+        let loro = LoroDoc::new();
+        loro.set_peer_id(1).unwrap();
+        let text = loro.get_text("text");
+        loro.with_txn(|txn| text.insert(txn, 0, "hel")).unwrap();
+        loro.with_txn(|txn| {
+            text.insert(txn, 3, "lo, world")?;
+            text.mark(txn, 0, 5, "bold", true.into(), TextStyleInfoFlag::BOLD)
+        })
+        .unwrap();
+        assert_eq!(text.next_word_boundary(0), 5);
+        assert_eq!(text.next_word_boundary(2), 5);
+        assert_eq!(text.next_word_boundary(5), 12);
+        assert_eq!(text.next_word_boundary(12), 12);
+        assert_eq!(
+            text.next_word_boundary_id(loro.oplog(), 0),
+            Some(ID::new(1, 4))
+        );
+        assert_eq!(
+            text.next_word_boundary_id(loro.oplog(), 5),
+            Some(ID::new(1, 11))
+        );
+
+        // the id still points to the end of the same word after it's edited
+        loro.with_txn(|txn| text.insert(txn, 2, "X")).unwrap();
+        assert_eq!(text.next_word_boundary(0), 6);
+        assert_eq!(
+            text.next_word_boundary_id(loro.oplog(), 0),
+            Some(ID::new(1, 4))
+        );
+
+        let empty = loro.get_text("empty");
+        assert_eq!(empty.next_word_boundary(0), 0);
+        assert_eq!(empty.next_word_boundary_id(loro.oplog(), 0), None);
+    }
+
+    #[test]
+    fn text_insert_chars() {
+        let loro = LoroDoc::new();
+        loro.set_peer_id(1).unwrap();
+        let text = loro.get_text("text");
+        loro.with_txn(|txn| text.insert_chars(txn, 0, std::iter::empty()))
+            .unwrap();
+        assert!(loro.oplog_vv().is_empty());
+
+        // longer than the chunk of the arena, with multi-byte chars
+        let s: String = "a你好😀".chars().cycle().take(200).collect();
+        loro.with_txn(|txn| text.insert_chars(txn, 0, s.chars()))
+            .unwrap();
+        loro.with_txn(|txn| text.insert_chars(txn, 1, "xy".chars()))
+            .unwrap();
+        let expected = format!("axy{}", &s[1..]);
+        assert_eq!(&**text.get_value().as_string().unwrap(), &expected);
+        assert_eq!(text.len_unicode(), 202);
+
+        let loro2 = LoroDoc::new();
+        loro2
+            .import(&loro.export_from(&Default::default()))
+            .unwrap();
+        assert_eq!(
+            &**loro2.get_text("text").get_value().as_string().unwrap(),
+            &expected
+        );
+
+        // the iterator reads the doc while the chars are inserted
+        let chars = "ab".chars().inspect(|_| {
+            loro.text_arena_stats();
+        });
+        loro.with_txn(|txn| text.insert_chars(txn, 0, chars))
+            .unwrap();
+        assert_eq!(text.len_unicode(), 204);
+    }
+
+    #[test]
+    fn text_slice() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        let long = "0123456789".repeat(1000);
+        text.insert_(0, &long).unwrap();
+        text.insert_(3, "你好").unwrap();
+        text.mark_(0, 8, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        assert_eq!(text.slice(5, 10).unwrap(), "34567");
+        assert_eq!(text.slice(2, 6).unwrap(), "2你好3");
+        assert_eq!(text.slice(10000, 20000).unwrap(), "89");
+        assert_eq!(text.slice(7, 7).unwrap(), "");
+        assert_eq!(
+            text.slice(0, usize::MAX).unwrap().len(),
+            long.len() + "你好".len()
+        );
+    }
+
+    #[test]
+    fn text_slice_utf16_in_surrogate_pair() {
+        // the wasm path of `slice` converts the UTF-16 range without panicking
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "a😀b").unwrap();
+        text.with_state(|state| {
+            assert_eq!(
+                state.checked_utf16_range_to_unicode_range(1..4).unwrap(),
+                1..3
+            );
+            assert!(matches!(
+                state.checked_utf16_range_to_unicode_range(2..4),
+                Err(LoroError::ArgErr(_))
+            ));
+            assert!(matches!(
+                state.checked_utf16_range_to_unicode_range(0..2),
+                Err(LoroError::ArgErr(_))
+            ));
+            assert!(matches!(
+                state.checked_utf16_range_to_unicode_range(0..5),
+                Err(LoroError::OutOfBound { pos: 5, len: 4 })
+            ));
+        });
+    }
+
+    #[test]
+    fn text_insert_many() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_many_(&[(0, "world"), (0, "hello "), (11, "!")])
+            .unwrap();
+        loro.commit_then_renew();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "hello world!"
+        );
+        assert_eq!(
+            loro.oplog().lock().unwrap().changes()[&loro.peer_id()].len(),
+            1
+        );
+        // a position can point into the text inserted by the same batch
+        text.insert_many_(&[(12, " ac"), (14, "b"), (0, "")])
+            .unwrap();
+        loro.commit_then_renew();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "hello world! abc"
+        );
+        let remote = LoroDoc::new();
+        remote
+            .import(&loro.export_from(&Default::default()))
+            .unwrap();
+        assert_eq!(remote.get_deep_value(), loro.get_deep_value());
+        assert!(text.insert_many_(&[(0, "a"), (100, "b")]).is_err());
+    }
+
+    #[test]
+    fn text_replace() {
+        use crate::{delta::DeltaItem, event::Diff};
+        use std::sync::{Arc, Mutex};
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "hello world").unwrap();
+        loro.commit_then_renew();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        loro.subscribe_root(Arc::new(move |e| {
+            events_clone.lock().unwrap().push(e.container.diff.clone());
+        }));
+        text.replace_(6, 5, "loro").unwrap();
+        loro.commit_then_renew();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "hello loro");
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let Diff::Text(delta) = &events[0] else {
+            unreachable!()
+        };
+        let items: Vec<_> = delta.iter().collect();
+        assert_eq!(items.len(), 3);
+        assert!(matches!(items[0], DeltaItem::Retain { retain: 6, .. }));
+        assert!(items[1..]
+            .iter()
+            .any(|x| matches!(x, DeltaItem::Delete { delete: 5, .. })));
+        assert!(items[1..]
+            .iter()
+            .any(|x| matches!(x, DeltaItem::Insert { .. })));
+        assert!(text.replace_(8, 5, "x").is_err());
+    }
+
+    #[test]
+    fn text_delete_ranges() {
+        use crate::{event::Diff, ApplyDiff};
+        use loro_common::LoroValue;
+        use std::sync::{Arc, Mutex};
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "0123456789").unwrap();
+        loro.commit_then_renew();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        loro.subscribe_root(Arc::new(move |e| {
+            events_clone.lock().unwrap().push(e.container.diff.clone());
+        }));
+        assert!(text.delete_ranges_(&[(1, 3), (3, 2)]).is_err());
+        assert!(text.delete_ranges_(&[(1, 1), (8, 3)]).is_err());
+        assert!(matches!(
+            text.delete_ranges_(&[(1, usize::MAX), (3, 1)]),
+            Err(LoroError::OutOfBound { .. })
+        ));
+        text.delete_ranges_(&[(8, 1), (1, 2), (5, 1)]).unwrap();
+        loro.commit_then_renew();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "034679");
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let Diff::Text(delta) = &events[0] else {
+            unreachable!()
+        };
+        let mut value = LoroValue::String(Arc::new("0123456789".into()));
+        value.apply_diff(&[Diff::Text(delta.clone())]);
+        assert_eq!(value.as_string().unwrap().as_str(), "034679");
+    }
+
+    #[test]
+    fn text_value_after_edits() {
+        let a = LoroDoc::new_auto_commit();
+        let text = a.get_text("text");
+        text.insert_(0, "hello").unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "hello");
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "hello");
+        text.delete_(0, 1).unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "ello");
+        text.mark_(0, 2, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "ello");
+
+        // remote changes
+        let b = LoroDoc::new_auto_commit();
+        b.import(&a.export_snapshot()).unwrap();
+        let b_text = b.get_text("text");
+        assert_eq!(b_text.get_value().as_string().unwrap().as_str(), "ello");
+        b_text.insert_(4, "!").unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "ello!");
+
+        // checkout
+        a.commit_then_stop();
+        let mut a = a;
+        a.checkout(&Frontiers::from(ID::new(a.peer_id(), 4)))
+            .unwrap();
+        assert_eq!(
+            a.get_text("text").get_value().as_string().unwrap().as_str(),
+            "hello"
+        );
+        a.checkout_to_latest();
+        assert_eq!(
+            a.get_text("text").get_value().as_string().unwrap().as_str(),
+            "ello!"
+        );
+    }
+
+    #[test]
+    fn text_insert_at_line_col() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        assert_eq!(text.line_col_to_pos(0, 0).unwrap(), 0);
+        assert!(matches!(
+            text.line_col_to_pos(1, 0),
+            Err(LoroError::OutOfBound { pos: 1, len: 1 })
+        ));
+        text.insert_at_line_col_(0, 0, "ab\r\n你好\n").unwrap();
+        assert_eq!(text.line_col_to_pos(1, 0).unwrap(), 4);
+        assert_eq!(text.line_col_to_pos(2, 0).unwrap(), 7);
+        text.insert_at_line_col_(0, 2, "c").unwrap();
+        text.insert_at_line_col_(1, 1, "X").unwrap();
+        text.insert_at_line_col_(2, 0, "end").unwrap();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "abc\r\n你X好\nend"
+        );
+        // the lines span several chunks now
+        assert_eq!(text.line_col_to_pos(0, 3).unwrap(), 3);
+        assert_eq!(text.line_col_to_pos(1, 3).unwrap(), 8);
+        assert_eq!(text.line_col_to_pos(2, 3).unwrap(), 12);
+        // `\r` is not a column of the line
+        assert!(matches!(
+            text.insert_at_line_col_(0, 4, "?"),
+            Err(LoroError::OutOfBound { pos: 4, len: 3 })
+        ));
+        assert!(matches!(
+            text.insert_at_line_col_(3, 0, "?"),
+            Err(LoroError::OutOfBound { pos: 3, len: 3 })
+        ));
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "abc\r\n你X好\nend"
+        );
+    }
+
+    #[test]
+    fn text_find() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        assert_eq!(text.find("a", 0), None);
+        assert_eq!(text.find("", 0), Some(0));
+        text.insert_(0, "ab你ab").unwrap();
+        // split the text into several chunks
+        text.insert_(1, "b").unwrap();
+        text.insert_(3, "你").unwrap();
+        text.delete_(1, 1).unwrap();
+        text.delete_(2, 1).unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "ab你ab");
+        assert_eq!(text.find("b你a", 0), Some(1));
+        assert_eq!(text.find("ab", 1), Some(3));
+        assert_eq!(text.find("ab", 4), None);
+        assert_eq!(text.find("", 2), Some(2));
+        assert_eq!(text.find("a", 100), None);
+        assert_eq!(text.find_all("ab"), vec![0, 3]);
+        assert_eq!(text.find_all("你"), vec![2]);
+        assert!(text.find_all("").is_empty());
+        text.insert_(5, "aaa").unwrap();
+        assert_eq!(text.find_all("aa"), vec![5]);
+        assert_eq!(text.find("", 8), Some(8));
+        // a failed partial match resumes from its longest matching suffix
+        text.insert_(8, "b").unwrap();
+        assert_eq!(text.find("aab", 0), Some(6));
+        // the anchors of a style don't break a match
+        text.mark_(4, 7, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        assert_eq!(text.find_all("baa"), vec![4]);
+        assert_eq!(text.find("aab", 0), Some(6));
+    }
+
+    #[test]
+    fn text_blame() {
+        let mut a = LoroDoc::new();
+        a.set_peer_id(1).unwrap();
+        let mut txn = a.txn().unwrap();
+        txn.set_timestamp(100);
+        a.get_text("text").insert(&mut txn, 0, "hello").unwrap();
+        txn.commit().unwrap();
+        let b = LoroDoc::new();
+        b.set_peer_id(2).unwrap();
+        let mut txn = b.txn().unwrap();
+        txn.set_timestamp(200);
+        b.get_text("text").insert(&mut txn, 0, " world").unwrap();
+        txn.commit().unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+
+        let text = a.get_text("text");
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "hello world"
+        );
+        assert_eq!(text.blame(a.oplog(), 0), Some((1, 100, ID::new(1, 0))));
+        assert_eq!(text.blame(a.oplog(), 4), Some((1, 100, ID::new(1, 4))));
+        assert_eq!(text.blame(a.oplog(), 6), Some((2, 200, ID::new(2, 1))));
+        assert_eq!(text.blame(a.oplog(), 11), None);
+
+        // the chars are resolved on the checked out version
+        a.checkout(&ID::new(1, 4).into()).unwrap();
+        assert_eq!(text.blame(a.oplog(), 4), Some((1, 100, ID::new(1, 4))));
+        assert_eq!(text.blame(a.oplog(), 5), None);
+    }
+
+    #[test]
+    fn text_cursor_with_cached_tracker() {
+        // The cached tracker is extended with the new ops on every query,
+        // including the ops merged into a change that is partially tracked.
+        let a = LoroDoc::new_auto_commit();
+        a.set_peer_id(1).unwrap();
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        let text = a.get_text("text");
+        for i in 0..20 {
+            text.insert_(i % (text.len_unicode() + 1), "ab").unwrap();
+            if i % 4 == 3 {
+                text.delete_(1, 2).unwrap();
+            }
+            a.commit_then_renew();
+            let b_text = b.get_text("text");
+            b_text.insert_(0, &i.to_string()).unwrap();
+            b.commit_then_renew();
+            if i % 3 == 0 {
+                a.import(&b.export_from(&a.oplog_vv())).unwrap();
+                b.import(&a.export_from(&b.oplog_vv())).unwrap();
+            }
+
+            let fresh = LoroDoc::from_snapshot(&a.export_snapshot()).unwrap();
+            let fresh_text = fresh.get_text("text");
+            for pos in 0..=text.len_unicode() {
+                let cursor = text.cursor_at(a.oplog(), pos).unwrap();
+                assert_eq!(fresh_text.cursor_at(fresh.oplog(), pos).unwrap(), cursor);
+                assert_eq!(text.resolve_cursor(a.oplog(), &cursor), Some((pos, false)));
+            }
+        }
+    }
+
+    #[test]
+    fn text_cursor() {
+        let mut a = LoroDoc::new_auto_commit();
+        a.set_peer_id(1).unwrap();
+        let text = a.get_text("text");
+        text.insert_(0, "hello world").unwrap();
+        a.commit_then_renew();
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        b.import(&a.export_snapshot()).unwrap();
+
+        let cursor = text.cursor_at(a.oplog(), 6).unwrap();
+        let end = text.cursor_at(a.oplog(), 11).unwrap();
+        assert_eq!(end.id, None);
+        assert!(text.cursor_at(a.oplog(), 12).is_err());
+
+        // concurrent edits before the cursor
+        b.get_text("text").insert_(0, "> ").unwrap();
+        b.get_text("text").delete_(2, 1).unwrap();
+        text.mark_(0, 5, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        a.commit_then_renew();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "> ello world"
+        );
+        assert_eq!(text.resolve_cursor(a.oplog(), &cursor), Some((7, false)));
+
+        // the anchored char is deleted
+        text.delete_(7, 2).unwrap();
+        a.commit_then_renew();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "> ello rld");
+        assert_eq!(text.resolve_cursor(a.oplog(), &cursor), Some((7, true)));
+        assert_eq!(text.resolve_cursor(a.oplog(), &end), Some((10, false)));
+
+        // detached at the version before the concurrent edits, the indexes
+        // follow the checked out state instead of the latest version
+        a.checkout(&Frontiers::from_id(ID::new(1, 10))).unwrap();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "hello world"
+        );
+        assert_eq!(text.resolve_cursor(a.oplog(), &cursor), Some((6, false)));
+        assert_eq!(text.cursor_at(a.oplog(), 6).unwrap(), cursor);
+        assert_eq!(
+            text.cursor_at(a.oplog(), 1).unwrap().id,
+            Some(ID::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn text_with_str() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.with_str(|s| assert_eq!(s, ""));
+        text.insert_(0, "hello").unwrap();
+        text.with_state_mut(|state| {
+            assert!(matches!(
+                state.as_str_cow(),
+                std::borrow::Cow::Borrowed("hello")
+            ))
+        });
+        text.insert_(0, "abc").unwrap();
+        text.mark_(0, 2, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        text.with_str(|s| assert_eq!(s, "abchello"));
+        assert_eq!(
+            text.with_str(|s| s.to_string()),
+            text.get_value().as_string().unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn text_char_at() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        assert_eq!(text.char_at(0), None);
+        text.insert_(0, "hello world").unwrap();
+        text.insert_(5, "😀你").unwrap();
+        text.mark_(2, 9, "bold", true.into(), TextStyleInfoFlag::BOLD)
+            .unwrap();
+        text.insert_(0, "🎉").unwrap();
+        let s = text.get_value().as_string().unwrap().to_string();
+        for (i, c) in s.chars().enumerate() {
+            assert_eq!(text.char_at(i), Some(c));
+        }
+
+        assert_eq!(text.char_at(s.chars().count()), None);
+    }
+
+    #[test]
+    fn text_utf16_insert_delete() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "a😀b你好c").unwrap();
+        // "a" 1, "😀" 2, "b" 1, "你" 1, "好" 1, "c" 1
+        text.insert_utf16_(3, "!").unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "a😀!b你好c");
+        text.insert_utf16_(8, "🎉").unwrap();
+        assert_eq!(
+            text.get_value().as_string().unwrap().as_str(),
+            "a😀!b你好c🎉"
+        );
+        text.delete_utf16_(1, 2).unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "a!b你好c🎉");
+        text.delete_utf16_(3, 2).unwrap();
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "a!bc🎉");
+        assert!(text.insert_utf16_(7, "x").is_err());
+        assert!(matches!(
+            text.delete_utf16_(1, usize::MAX),
+            Err(LoroError::OutOfBound { .. })
+        ));
+        assert!(matches!(
+            text.delete_utf16_(4, 3),
+            Err(LoroError::OutOfBound { pos: 7, len: 6 })
+        ));
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "a!bc🎉");
+    }
+
+    #[test]
+    #[should_panic(expected = "surrogate pair")]
+    fn text_utf16_delete_to_surrogate_pair() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "a😀b").unwrap();
+        text.delete_utf16_(0, 2).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "surrogate pair")]
+    fn text_utf16_insert_in_surrogate_pair() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "a😀b").unwrap();
+        text.insert_utf16_(2, "x").unwrap();
+    }
+
+    #[test]
+    fn text_consistent_reads() {
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "你好 world").unwrap();
+        let (len, s, value) = text.with_state_mut(|state| {
+            (
+                state.len_unicode(),
+                state.as_string(),
+                state.get_richtext_value(),
+            )
+        });
+        assert_eq!(len, text.len_unicode());
+        assert_eq!(s, "你好 world");
+        assert_eq!(value, text.get_richtext_value());
+    }
+
+    #[test]
+    fn recover_from_poisoned_state() {
+        let loro = LoroDoc::new();
+        let text = loro.get_text("text");
+        loro.with_txn(|txn| text.insert(txn, 0, "hello")).unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            text.with_state(|_| panic!("panic while holding the lock"))
+        }));
+        assert!(result.is_err());
+        assert!(loro.app_state().is_poisoned());
+        loro.with_txn(|txn| text.insert(txn, 5, " world")).unwrap();
+        assert_eq!(&**text.get_value().as_string().unwrap(), "hello world");
+        assert!(!loro.app_state().is_poisoned());
+    }
+
+    #[test]
+    fn poisoned_oplog_is_not_reused() {
+        let src = LoroDoc::new_auto_commit();
+        src.get_text("text").insert_(0, "hello").unwrap();
+        let loro = LoroDoc::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _oplog = loro.oplog().lock().unwrap();
+            panic!("panic while holding the lock")
+        }));
+        assert!(result.is_err());
+        // the oplog may be half updated, so it fails instead of recovering
+        assert!(matches!(
+            loro.import(&src.export_from(&Default::default())),
+            Err(LoroError::LockError)
+        ));
+        assert!(loro.oplog().is_poisoned());
+    }
+
+    #[test]
+    fn recover_from_panic_in_txn() {
+        // the txn dropped by the panic is rolled back
+        let loro = LoroDoc::new();
+        let text = loro.get_text("text");
+        loro.with_txn(|txn| text.insert(txn, 0, "hello")).unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut txn = loro.txn().unwrap();
+            text.insert(&mut txn, 5, " world").unwrap();
+            text.with_state(|_| panic!("panic while holding the lock"))
+        }));
+        assert!(result.is_err());
+        loro.with_txn(|txn| text.insert(txn, 5, "!")).unwrap();
+        assert_eq!(&**text.get_value().as_string().unwrap(), "hello!");
+
+        // the auto-commit txn is rolled back and replaced
+        let loro = LoroDoc::new_auto_commit();
+        let text = loro.get_text("text");
+        text.insert_(0, "hello").unwrap();
+        loro.commit_then_renew();
+        text.insert_(5, " world").unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            text.with_state(|_| panic!("panic while holding the lock"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(&**text.get_value().as_string().unwrap(), "hello");
+        text.insert_(5, "!").unwrap();
+        loro.commit_then_renew();
+        assert_eq!(&**text.get_value().as_string().unwrap(), "hello!");
+        let peer = LoroDoc::new();
+        peer.import(&loro.export_from(&Default::default())).unwrap();
+        assert_eq!(peer.get_deep_value(), loro.get_deep_value());
+    }
+
     #[test]
     fn richtext_handler_concurrent() {
         let loro = LoroDoc::new();
@@ -1471,6 +2726,64 @@ mod test {
         loro2.import(&bytes).unwrap();
     }
 
+    #[test]
+    fn map_bulk_ops() {
+        use std::sync::{Arc, Mutex};
+        let loro = LoroDoc::new_auto_commit();
+        let map = loro.get_map("map");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        loro.subscribe_root(Arc::new(move |e| {
+            events_clone.lock().unwrap().push(e.doc.diff.len());
+        }));
+        map.insert_all_([("a", 1.into()), ("b", 2.into()), ("c", 3.into())])
+            .unwrap();
+        loro.commit_then_renew();
+        assert_eq!(*events.lock().unwrap(), vec![1]);
+        assert_eq!(
+            loro.oplog().lock().unwrap().changes()[&loro.peer_id()].len(),
+            1
+        );
+
+        // only "a" is removed and "b" is updated, "c" is unchanged
+        map.sync_to_(&fx_map!("b".into() => 20.into(), "c".into() => 3.into()))
+            .unwrap();
+        loro.commit_then_renew();
+        assert_eq!(loro.oplog_vv().get(&loro.peer_id()), Some(&5));
+        assert_eq!(events.lock().unwrap().len(), 2);
+        assert_eq!(map.get_value().to_json_value(), json!({"b": 20, "c": 3}));
+
+        map.remove_all_(["b", "c"]).unwrap();
+        loro.commit_then_renew();
+        assert_eq!(map.get_value().to_json_value(), json!({}));
+    }
+
+    #[test]
+    fn map_compare_and_set() {
+        let loro = LoroDoc::new_auto_commit();
+        let map = loro.get_map("map");
+        assert!(map.compare_and_set_("a", None, 1.into()).unwrap());
+        assert!(!map.compare_and_set_("a", None, 2.into()).unwrap());
+        assert!(!map.compare_and_set_("a", Some(2.into()), 3.into()).unwrap());
+        assert!(map.compare_and_set_("a", Some(1.into()), 3.into()).unwrap());
+        map.delete_("a").unwrap();
+        assert!(map.compare_and_set_("a", None, 4.into()).unwrap());
+        loro.commit_then_renew();
+        assert_eq!(map.get_value().to_json_value(), json!({"a": 4}));
+    }
+
+    #[test]
+    fn map_keys_and_entries() {
+        let loro = LoroDoc::new_auto_commit();
+        let map = loro.get_map("map");
+        map.insert_("a", 1.into()).unwrap();
+        map.insert_("b", 2.into()).unwrap();
+        map.delete_("a").unwrap();
+        loro.commit_then_renew();
+        assert_eq!(map.keys(), vec![crate::InternalString::from("b")]);
+        assert_eq!(map.entries(), vec![("b".into(), 2.into())]);
+    }
+
     #[test]
     fn tree_meta_event() {
         use std::sync::Arc;
@@ -9,7 +9,7 @@
 pub mod arena;
 pub mod diff_calc;
 pub mod handler;
-pub use event::{ContainerDiff, DiffEvent, DocDiff};
+pub use event::{ContainerCreated, ContainerDiff, DiffEvent, DocDiff, SignalEvent};
 pub use handler::{ListHandler, MapHandler, TextHandler, TreeHandler};
 pub use loro::LoroDoc;
 pub use oplog::OpLog;
@@ -1,28 +1,35 @@
 use std::{
     borrow::Cow,
     cmp::Ordering,
+    hash::Hasher,
     sync::{Arc, Mutex, Weak},
 };
 
+use fxhash::{FxHashMap, FxHasher64};
+
 use loro_common::{ContainerID, ContainerType, LoroResult, LoroValue};
 
 use crate::{
     arena::SharedArena,
     change::Timestamp,
+    configure::Configure,
     container::{idx::ContainerIdx, IntoContainerId},
-    encoding::{EncodeMode, ENCODE_SCHEMA_VERSION, MAGIC_BYTES},
+    encoding::{
+        append_checksum, parse_encode_header, EncodeMode, ENCODE_SCHEMA_VERSION, MAGIC_BYTES,
+    },
     handler::TextHandler,
     handler::TreeHandler,
     id::PeerID,
+    utils::lock::{LockOrErr, LockOrRecover, Recover},
     version::Frontiers,
     InternalString, LoroError, VersionVector,
 };
 
 use super::{
     diff_calc::DiffCalculator,
-    encoding::encode_snapshot::{decode_app_snapshot, encode_app_snapshot},
-    event::InternalDocDiff,
-    obs::{Observer, SubID, Subscriber},
+    encoding::encode_snapshot::{decode_app_snapshot, encode_app_snapshot, encode_snapshot},
+    event::{ContainerDiff, Diff, Index, InternalDocDiff},
+    obs::{NotifyMode, Observer, SubID, Subscriber},
     oplog::OpLog,
     state::DocState,
     txn::Transaction,
@@ -90,6 +97,10 @@ impl LoroDoc {
         doc
     }
 
+    /// Create a doc from bytes exported by [`LoroDoc::export_snapshot`].
+    ///
+    /// The materialized state stored in the snapshot is decoded directly,
+    /// so no ops need to be replayed.
     pub fn from_snapshot(bytes: &[u8]) -> LoroResult<Self> {
         let doc = Self::new();
         let (input, mode) = parse_encode_header(bytes)?;
@@ -107,7 +118,7 @@ impl LoroDoc {
     /// Is the document empty? (no ops)
     #[inline(always)]
     pub fn can_reset_with_snapshot(&self) -> bool {
-        self.oplog.lock().unwrap().is_empty() && self.state.lock().unwrap().is_empty()
+        self.oplog.lock().unwrap().is_empty() && self.state.lock_or_recover().is_empty()
     }
 
     /// Whether [OpLog] ans [DocState] are detached.
@@ -116,6 +127,17 @@ impl LoroDoc {
         self.detached
     }
 
+    #[inline]
+    pub fn configure(&self) -> Configure {
+        self.oplog.lock().unwrap().configure.clone()
+    }
+
+    /// Set the [Configure] of this doc. It only affects the changes created afterwards.
+    #[inline]
+    pub fn set_configure(&self, configure: Configure) {
+        self.oplog.lock().unwrap().configure = configure;
+    }
+
     #[allow(unused)]
     pub(super) fn from_existing(oplog: OpLog, state: DocState) -> Self {
         let obs = Observer::new(oplog.arena.clone());
@@ -133,19 +155,25 @@ impl LoroDoc {
 
     #[inline(always)]
     pub fn peer_id(&self) -> PeerID {
-        self.state.lock().unwrap().peer
+        self.state.lock_or_recover().peer
     }
 
+    /// Set the peer id used by the following local changes.
+    ///
+    /// The ids of existing changes cannot be reassigned: peer ids are part of the deps of
+    /// other peers' changes, of container ids and of tree ids, and other peers keep
+    /// referring to them. If an imported history uses the local peer id, change the
+    /// local peer id instead.
     #[inline(always)]
     pub fn set_peer_id(&self, peer: PeerID) -> LoroResult<()> {
         if self.auto_commit {
-            let mut doc_state = self.state.lock().unwrap();
+            let mut doc_state = self.state.lock_or_recover();
             doc_state.peer = peer;
             drop(doc_state);
 
-            let txn = self.txn.lock().unwrap().take();
+            let txn = self.txn.lock_or_recover().take();
             if let Some(txn) = txn {
-                if !txn.is_empty() {
+                if !txn.is_empty() && !txn.is_rolled_back() {
                     txn.commit().unwrap();
                 } else {
                     txn.abort();
@@ -153,11 +181,11 @@ impl LoroDoc {
             }
 
             let new_txn = self.txn().unwrap();
-            self.txn.lock().unwrap().replace(new_txn);
+            self.txn.lock_or_recover().replace(new_txn);
             return Ok(());
         }
 
-        let mut doc_state = self.state.lock().unwrap();
+        let mut doc_state = self.state.lock_or_recover();
         if doc_state.is_in_txn() {
             return Err(LoroError::TransactionError(
                 "Cannot change peer id during transaction"
@@ -183,7 +211,7 @@ impl LoroDoc {
     /// Get the timestamp of the current state.
     /// It's the last edit time of the [DocState].
     pub fn state_timestamp(&self) -> Timestamp {
-        let f = &self.state.lock().unwrap().frontiers;
+        let f = &self.state.lock_or_recover().frontiers;
         self.oplog.lock().unwrap().get_timestamp_of_version(f)
     }
 
@@ -197,6 +225,13 @@ impl LoroDoc {
         self.txn_with_origin("")
     }
 
+    /// Run `f` in a new transaction and commit it.
+    ///
+    /// All the edits in `f` are committed as one change, and subscribers receive a single
+    /// event in which the diffs of each container are composed. To batch edits made by
+    /// nested helpers, pass the same `txn` down instead of opening new transactions.
+    ///
+    /// If `f` returns an error, the edits made so far are still committed.
     #[inline(always)]
     pub fn with_txn<F, R>(&self, f: F) -> LoroResult<R>
     where
@@ -210,7 +245,7 @@ impl LoroDoc {
 
     pub fn start_auto_commit(&mut self) {
         self.auto_commit = true;
-        let mut self_txn = self.txn.try_lock().unwrap();
+        let mut self_txn = self.txn.try_lock_or_recover();
         if self_txn.is_some() || self.detached {
             return;
         }
@@ -248,7 +283,7 @@ impl LoroDoc {
             return;
         }
 
-        let mut txn_guard = self.txn.try_lock().unwrap();
+        let mut txn_guard = self.txn.try_lock_or_recover();
         let txn = txn_guard.take();
         drop(txn_guard);
         let Some(mut txn) = txn else {
@@ -264,9 +299,14 @@ impl LoroDoc {
             txn.set_timestamp(timestamp);
         }
 
-        txn.commit().unwrap();
+        if txn.is_rolled_back() {
+            txn.abort();
+        } else {
+            txn.commit().unwrap();
+        }
+
         if immediate_renew {
-            let mut txn_guard = self.txn.try_lock().unwrap();
+            let mut txn_guard = self.txn.try_lock_or_recover();
             assert!(!self.detached);
             *txn_guard = Some(self.txn().unwrap());
         }
@@ -281,7 +321,7 @@ impl LoroDoc {
     /// Afterwards, the users need to call `self.renew_txn_after_commit()` to resume the continuous transaction.
     #[inline]
     pub fn abort_txn(&self) {
-        if let Some(mut txn) = self.txn.lock().unwrap().take() {
+        if let Some(mut txn) = self.txn.lock_or_recover().take() {
             txn.take_on_commit();
             txn.abort();
         }
@@ -289,7 +329,7 @@ impl LoroDoc {
 
     pub fn renew_txn_if_auto_commit(&self) {
         if self.auto_commit && !self.detached {
-            let mut self_txn = self.txn.try_lock().unwrap();
+            let mut self_txn = self.txn.try_lock_or_recover();
             if self_txn.is_some() {
                 return;
             }
@@ -323,9 +363,13 @@ impl LoroDoc {
 
         let obs = self.observer.clone();
         txn.set_on_commit(Box::new(move |state| {
-            let mut state = state.try_lock().unwrap();
+            let mut state = state.try_lock_or_recover();
+            let signals = state.take_signals();
             let events = state.take_events();
             drop(state);
+            for signal in signals {
+                obs.emit_signal(signal);
+            }
             for event in events {
                 obs.emit(event);
             }
@@ -341,7 +385,7 @@ impl LoroDoc {
 
     #[inline]
     pub fn get_state_deep_value(&self) -> LoroValue {
-        self.state.lock().unwrap().get_deep_value()
+        self.state.lock_or_recover().get_deep_value()
     }
 
     #[inline(always)]
@@ -349,6 +393,11 @@ impl LoroDoc {
         &self.oplog
     }
 
+    /// Export all the changes that are not included in `vv`.
+    ///
+    /// The changes cannot be filtered by container. The ids of a peer's ops are contiguous
+    /// and a change may depend on ops of any container, so skipping ops would leave gaps
+    /// the receiver could never fill. Use separate docs for pieces that sync independently.
     pub fn export_from(&self, vv: &VersionVector) -> Vec<u8> {
         self.commit_then_stop();
         let ans = self.oplog.lock().unwrap().export_from(vv);
@@ -356,6 +405,21 @@ impl LoroDoc {
         ans
     }
 
+    /// Export the changes between `from` and `target`, excluding the changes after `target`.
+    pub fn export_to_frontier(
+        &self,
+        from: &VersionVector,
+        target: &Frontiers,
+    ) -> LoroResult<Vec<u8>> {
+        self.commit_then_stop();
+        let ans = self
+            .oplog
+            .lock_or_err()
+            .and_then(|oplog| oplog.export_to_frontier(from, target));
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     #[inline(always)]
     pub fn import(&self, bytes: &[u8]) -> Result<(), LoroError> {
         self.import_with(bytes, Default::default())
@@ -386,29 +450,11 @@ impl LoroDoc {
             EncodeMode::Updates | EncodeMode::RleUpdates | EncodeMode::CompressedRleUpdates => {
                 // TODO: need to throw error if state is in transaction
                 debug_log::group!("import to {}", self.peer_id());
-                let mut oplog = self.oplog.lock().unwrap();
+                let mut oplog = self.oplog.lock_or_err()?;
                 let old_vv = oplog.vv().clone();
                 let old_frontiers = oplog.frontiers().clone();
                 oplog.decode(bytes)?;
-                if !self.detached {
-                    let mut diff = DiffCalculator::default();
-                    let diff = diff.calc_diff_internal(
-                        &oplog,
-                        &old_vv,
-                        Some(&old_frontiers),
-                        oplog.vv(),
-                        Some(oplog.dag.get_frontiers()),
-                    );
-                    let mut state = self.state.lock().unwrap();
-                    state.apply_diff(InternalDocDiff {
-                        origin,
-                        local: false,
-                        diff: (diff).into(),
-                        from_checkout: false,
-                        new_version: Cow::Owned(oplog.frontiers().clone()),
-                    });
-                }
-
+                self.apply_imported_changes(&oplog, &old_vv, &old_frontiers, origin);
                 debug_log::group_end!();
             }
             EncodeMode::Snapshot => {
@@ -417,7 +463,7 @@ impl LoroDoc {
                 } else {
                     let app = LoroDoc::new();
                     decode_app_snapshot(&app, input, false)?;
-                    let oplog = self.oplog.lock().unwrap();
+                    let oplog = self.oplog.lock_or_err()?;
                     // TODO: PERF: the ser and de can be optimized out
                     let updates = app.export_from(oplog.vv());
                     drop(oplog);
@@ -430,13 +476,91 @@ impl LoroDoc {
         Ok(())
     }
 
+    /// Apply the changes imported into `oplog` after `old_vv` to the state, unless detached.
+    fn apply_imported_changes(
+        &self,
+        oplog: &OpLog,
+        old_vv: &VersionVector,
+        old_frontiers: &Frontiers,
+        origin: InternalString,
+    ) {
+        if self.detached {
+            return;
+        }
+
+        let applied = oplog.configure.single_writer
+            && self
+                .state
+                .lock_or_recover()
+                .apply_linear_changes(oplog, old_vv);
+        if !applied {
+            let mut diff = DiffCalculator::default();
+            let diff = diff.calc_diff_internal(
+                oplog,
+                old_vv,
+                Some(old_frontiers),
+                oplog.vv(),
+                Some(oplog.dag.get_frontiers()),
+            );
+            let mut state = self.state.lock_or_recover();
+            state.apply_diff(InternalDocDiff {
+                origin,
+                local: false,
+                diff: (diff).into(),
+                from_checkout: false,
+                new_version: Cow::Owned(oplog.frontiers().clone()),
+            });
+        }
+    }
+
+    /// Encode the pending changes, i.e. the imported changes whose deps are still
+    /// missing, so they can be restored by [LoroDoc::import_pending] after a restart.
+    pub fn export_pending(&self) -> Vec<u8> {
+        self.oplog.lock().unwrap().export_pending()
+    }
+
+    /// Restore the pending changes exported by [LoroDoc::export_pending].
+    ///
+    /// The changes whose deps are already included are applied right away, to both
+    /// the oplog and the state, like [LoroDoc::import]. The others stay pending.
+    pub fn import_pending(&self, data: &[u8]) -> LoroResult<()> {
+        self.commit_then_stop();
+        let mut oplog = self.oplog.lock_or_err()?;
+        let old_vv = oplog.vv().clone();
+        let old_frontiers = oplog.frontiers().clone();
+        let ans = oplog.import_pending(data);
+        if ans.is_ok() {
+            self.apply_imported_changes(&oplog, &old_vv, &old_frontiers, Default::default());
+        }
+
+        drop(oplog);
+        self.emit_events();
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     fn emit_events(&self) {
-        let events = self.state.lock().unwrap().take_events();
+        let mut state = self.state.lock_or_recover();
+        let signals = state.take_signals();
+        let events = state.take_events();
+        drop(state);
+        for signal in signals {
+            self.observer.emit_signal(signal);
+        }
         for event in events {
             self.observer.emit(event);
         }
     }
 
+    /// Export the whole history together with the current materialized state.
+    ///
+    /// Use [`LoroDoc::from_snapshot`] to load it without replaying the ops.
+    ///
+    /// The history cannot be truncated before a version. The [OpLog] must hold a complete
+    /// causal graph, since diffs and checkouts are calculated from the common ancestors,
+    /// so a doc that starts at a non-empty version couldn't merge concurrent changes made
+    /// before it. To ship the content without the history, create a new doc from
+    /// [`LoroDoc::get_deep_value`], which gives up syncing with the old one.
     pub fn export_snapshot(&self) -> Vec<u8> {
         self.commit_then_stop();
         debug_log::group!("export snapshot");
@@ -446,6 +570,7 @@ impl LoroDoc {
         ans.push(version);
         ans.push((EncodeMode::Snapshot).to_byte());
         ans.extend(encode_app_snapshot(self));
+        append_checksum(&mut ans);
         debug_log::group_end!();
         self.renew_txn_if_auto_commit();
         ans
@@ -460,7 +585,7 @@ impl LoroDoc {
     /// Get the version vector of the current [DocState]
     #[inline]
     pub fn state_vv(&self) -> VersionVector {
-        let f = &self.state.lock().unwrap().frontiers;
+        let f = &self.state.lock_or_recover().frontiers;
         self.oplog.lock().unwrap().dag.frontiers_to_vv(f).unwrap()
     }
 
@@ -508,6 +633,11 @@ impl LoroDoc {
         self.arena.register_container(&id)
     }
 
+    /// Get the frontiers of the [OpLog].
+    ///
+    /// The result is owned, so it can be kept across an await point and compared with
+    /// the live frontiers later. Use `Vec::from` to get the ids as a `Vec<ID>`, or
+    /// [Frontiers::encode] to store it.
     #[inline]
     pub fn oplog_frontiers(&self) -> Frontiers {
         self.oplog().lock().unwrap().frontiers().clone()
@@ -515,7 +645,7 @@ impl LoroDoc {
 
     #[inline]
     pub fn state_frontiers(&self) -> Frontiers {
-        self.state.lock().unwrap().frontiers.clone()
+        self.state.lock_or_recover().frontiers.clone()
     }
 
     /// - Ordering::Less means self is less than target or parallel
@@ -527,7 +657,7 @@ impl LoroDoc {
     }
 
     pub fn subscribe_root(&self, callback: Subscriber) -> SubID {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock_or_recover();
         if !state.is_recording() {
             state.start_recording();
         }
@@ -535,8 +665,13 @@ impl LoroDoc {
         self.observer.subscribe_root(callback)
     }
 
+    /// Subscribe to the changes of the container and its descendants.
+    ///
+    /// Events from the descendants have `from_children` set to true, so they can be
+    /// skipped if only the container itself is of interest. Use the returned [SubID]
+    /// to [LoroDoc::unsubscribe].
     pub fn subscribe(&self, container_id: &ContainerID, callback: Subscriber) -> SubID {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock_or_recover();
         if !state.is_recording() {
             state.start_recording();
         }
@@ -544,6 +679,29 @@ impl LoroDoc {
         self.observer.subscribe(container_id, callback)
     }
 
+    /// Subscribe to the changes of the root with the given [NotifyMode].
+    pub fn subscribe_root_with_mode(&self, mode: NotifyMode) -> SubID {
+        self.start_recording_for(&mode);
+        self.observer.subscribe_root_with_mode(mode)
+    }
+
+    /// Subscribe to the changes of the container with the given [NotifyMode].
+    ///
+    /// If all the subscribers are in [NotifyMode::Signal], the deltas of the
+    /// changes are not built.
+    pub fn subscribe_with_mode(&self, container_id: &ContainerID, mode: NotifyMode) -> SubID {
+        self.start_recording_for(&mode);
+        self.observer.subscribe_with_mode(container_id, mode)
+    }
+
+    fn start_recording_for(&self, mode: &NotifyMode) {
+        let mut state = self.state.lock_or_recover();
+        match mode {
+            NotifyMode::Delta(_) => state.start_recording(),
+            NotifyMode::Signal(_) => state.start_recording_signal(),
+        }
+    }
+
     #[inline]
     pub fn unsubscribe(&self, id: SubID) {
         self.observer.unsubscribe(id);
@@ -554,7 +712,7 @@ impl LoroDoc {
         self.commit_then_stop();
         let is_detached = self.is_detached();
         self.detach();
-        self.oplog.lock().unwrap().batch_importing = true;
+        self.oplog.lock_or_err()?.batch_importing = true;
         let mut err = None;
         for data in bytes.iter() {
             match self.import(data) {
@@ -565,7 +723,7 @@ impl LoroDoc {
             }
         }
 
-        let mut oplog = self.oplog.lock().unwrap();
+        let mut oplog = self.oplog.lock_or_err()?;
         oplog.batch_importing = false;
         oplog.dag.refresh_frontiers();
         drop(oplog);
@@ -585,13 +743,164 @@ impl LoroDoc {
     /// Get deep value of the document.
     #[inline]
     pub fn get_deep_value(&self) -> LoroValue {
-        self.state.lock().unwrap().get_deep_value()
+        self.state.lock_or_recover().get_deep_value()
     }
 
     /// Get deep value of the document with container id
     #[inline]
     pub fn get_deep_value_with_id(&self) -> LoroValue {
-        self.state.lock().unwrap().get_deep_value_with_id()
+        self.state.lock_or_recover().get_deep_value_with_id()
+    }
+
+    /// Get the ids of all the containers known by this doc, including the root containers.
+    ///
+    /// It may include containers that have been registered but have no ops yet,
+    /// e.g. a root container only fetched by [LoroDoc::get_text]. The deleted containers,
+    /// which are detached from the root containers in the current state, are excluded.
+    pub fn container_ids(&self) -> Vec<ContainerID> {
+        let ids = self.arena.with_idx_to_id(|ids| ids.clone());
+        let state = self.state.lock_or_recover();
+        ids.into_iter()
+            .enumerate()
+            .filter(|(index, id)| {
+                let idx = ContainerIdx::from_index_and_type(*index as u32, id.container_type());
+                !state.is_deleted(idx)
+            })
+            .map(|(_, id)| id)
+            .collect()
+    }
+
+    /// Delete the container `id` by deleting it from its parent, so that all the peers
+    /// converge on the deletion.
+    ///
+    /// The container is detached from the doc together with its descendants. Concurrent
+    /// edits to it are still merged, but they stay invisible, i.e. the deletion wins,
+    /// because a container can only be created once and can't be reattached.
+    ///
+    /// Root containers and the meta maps of tree nodes can't be deleted; delete the tree
+    /// node with [TreeHandler::delete] instead. It's a no-op if the container is already
+    /// detached.
+    ///
+    /// The container is marked as deleted, so it's excluded from [LoroDoc::container_ids].
+    /// Its state is kept in memory, because the history still refers to it, e.g. when
+    /// checking out an older version.
+    pub fn delete_container(&self, txn: &mut Transaction, id: &ContainerID) -> LoroResult<()> {
+        if matches!(id, ContainerID::Root { .. }) {
+            return Err(LoroError::ArgErr(
+                "Root containers cannot be deleted".into(),
+            ));
+        }
+
+        let idx = self
+            .arena
+            .id_to_idx(id)
+            .ok_or_else(|| LoroError::NotFoundError(format!("Container {}", id).into()))?;
+        let Some(parent) = self.arena.get_parent(idx) else {
+            return Err(LoroError::NotFoundError(
+                format!("Container {} is not attached to the doc", id).into(),
+            ));
+        };
+        if parent.get_type() == ContainerType::Tree {
+            return Err(LoroError::ArgErr(
+                "The meta of a tree node cannot be deleted, delete the node instead".into(),
+            ));
+        }
+
+        let Some(index) = self.state.lock_or_recover().get_index_in_parent(idx) else {
+            return Ok(());
+        };
+
+        let parent = self.arena.idx_to_id(parent).unwrap();
+        match index {
+            Index::Key(key) => self.get_map(parent).delete(txn, &key),
+            Index::Seq(pos) => self.get_list(parent).delete(txn, pos, 1),
+            Index::Node(_) => unreachable!(),
+        }
+    }
+
+    /// Delete the container `id` in the auto-commit transaction.
+    /// See [LoroDoc::delete_container].
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn delete_container_(&self, id: &ContainerID) -> LoroResult<()> {
+        let mut txn = self.txn.try_lock_or_recover();
+        if txn.as_ref().is_some_and(Transaction::is_rolled_back) {
+            txn.recover();
+        }
+
+        match &mut *txn {
+            Some(t) => self.delete_container(t, id),
+            None => Err(LoroError::AutoCommitNotStarted),
+        }
+    }
+
+    /// Get the `(total, live)` bytes of the text arena, for monitoring.
+    ///
+    /// `total` is all the text ever inserted into the doc, `live` is the text in the
+    /// current state of all the text containers. The arena is append-only and cannot
+    /// be compacted, so the difference grows with deletions and is only freed by
+    /// dropping the doc.
+    pub fn text_arena_stats(&self) -> (usize, usize) {
+        let total = self.arena.str_len_bytes();
+        let texts: Vec<_> = self
+            .container_ids()
+            .into_iter()
+            .filter(|id| id.container_type() == ContainerType::Text)
+            .filter_map(|id| self.arena.id_to_idx(&id))
+            .collect();
+        let mut state = self.state.lock_or_recover();
+        let live = texts
+            .into_iter()
+            .map(|idx| state.with_state_mut(idx, |s| s.as_richtext_state_mut().unwrap().len_utf8()))
+            .sum();
+        (total, live)
+    }
+
+    /// Get a hash of the current state of all the containers.
+    ///
+    /// It only depends on the state, so two peers with the same version vector have
+    /// the same fingerprint no matter in which order they received the changes.
+    /// Map entries are hashed in key order, tree nodes in id order, and empty root
+    /// containers are skipped, because they only mean that a peer has fetched the root
+    /// by name. The hash is stable across targets, e.g. native and wasm peers.
+    ///
+    /// Comparing it with [LoroDoc::check_fingerprint] is a cheap safety net against
+    /// convergence bugs.
+    pub fn fingerprint(&self) -> u64 {
+        let value = self.get_deep_value();
+        let mut hasher = FxHasher64::default();
+        let mut roots: Vec<_> = value
+            .as_map()
+            .unwrap()
+            .iter()
+            .filter(|(_, v)| !is_empty_container_value(v))
+            .collect();
+        roots.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        hasher.write_u64(roots.len() as u64);
+        for (name, v) in roots {
+            hash_bytes(name.as_bytes(), &mut hasher);
+            hash_value_canonical(v, &mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Compare [LoroDoc::fingerprint] with the one reported by another peer, e.g. the
+    /// sender of the last import. Log a warning and return `false` if they diverge.
+    ///
+    /// It's only meaningful when both peers have the same version vector.
+    pub fn check_fingerprint(&self, expected: u64) -> bool {
+        let actual = self.fingerprint();
+        if actual != expected {
+            tracing::warn!(
+                actual,
+                expected,
+                vv = ?self.oplog_vv(),
+                "The state diverges from the remote peer"
+            );
+            return false;
+        }
+
+        true
     }
 
     pub fn checkout_to_latest(&mut self) {
@@ -607,21 +916,23 @@ impl LoroDoc {
     /// Any further import will not be reflected on the [DocState], until user call [LoroDoc::attach()]
     pub fn checkout(&mut self, frontiers: &Frontiers) -> LoroResult<()> {
         self.commit_then_stop();
-        let oplog = self.oplog.lock().unwrap();
-        let mut state = self.state.lock().unwrap();
-        self.detached = true;
-        let mut calc = self.diff_calculator.lock().unwrap();
-        let before = &oplog.dag.frontiers_to_vv(&state.frontiers).unwrap();
-        let Some(after) = &oplog.dag.frontiers_to_vv(frontiers) else {
+        let oplog = self.oplog.lock_or_err()?;
+        let Some(after) = oplog.dag.frontiers_to_vv(frontiers) else {
+            drop(oplog);
+            self.renew_txn_if_auto_commit();
             return Err(LoroError::NotFoundError(
                 format!("Cannot find the specified version {:?}", frontiers).into_boxed_str(),
             ));
         };
+        let mut state = self.state.lock_or_recover();
+        self.detached = true;
+        let mut calc = self.diff_calculator.lock_or_recover();
+        let before = &oplog.dag.frontiers_to_vv(&state.frontiers).unwrap();
         let diff = calc.calc_diff_internal(
             &oplog,
             before,
             Some(&state.frontiers),
-            after,
+            &after,
             Some(frontiers),
         );
         state.apply_diff(InternalDocDiff {
@@ -631,7 +942,11 @@ impl LoroDoc {
             from_checkout: true,
             new_version: Cow::Owned(frontiers.clone()),
         });
+        let signals = state.take_signals();
         let events = state.take_events();
+        for signal in signals {
+            self.observer.emit_signal(signal);
+        }
         for event in events {
             self.observer.emit(event);
         }
@@ -654,23 +969,242 @@ impl LoroDoc {
     pub fn merge(&self, other: &Self) -> LoroResult<()> {
         self.import(&other.export_from(&self.oplog_vv()))
     }
+
+    /// Preview the result of importing `bytes` without changing this doc.
+    ///
+    /// The data is imported into a fork of the latest version of this doc.
+    /// The returned [ImportPreview] contains the resulting value and the
+    /// diffs of the changed containers. Call [LoroDoc::commit_preview] to
+    /// apply it, or just drop it to reject the import.
+    ///
+    /// The pending txn is not committed and its changes are not in the preview.
+    ///
+    /// NOTE: the fork is a copy of the whole doc, so its cost is proportional
+    /// to the size of the doc. If the state isn't at the latest version or the
+    /// pending txn has changed it, the state of the fork is rebuilt from the ops.
+    pub fn preview_import(&self, bytes: &[u8]) -> LoroResult<ImportPreview> {
+        let fork = self.fork_latest()?;
+        let diff = Arc::new(Mutex::new(Vec::new()));
+        let diff_cloned = diff.clone();
+        fork.subscribe_root(Arc::new(move |event| {
+            diff_cloned.lock_or_recover().push(event.container.clone());
+        }));
+        fork.import(bytes)?;
+        let value = fork.get_deep_value();
+        drop(fork);
+        let diff = std::mem::take(&mut *diff.lock_or_recover());
+        Ok(ImportPreview {
+            bytes: bytes.to_vec(),
+            value,
+            diff,
+        })
+    }
+
+    /// Fork the latest version of this doc, without committing the pending txn.
+    fn fork_latest(&self) -> LoroResult<LoroDoc> {
+        let (bytes, with_state) = {
+            let state = self.state.lock_or_recover();
+            let oplog = self.oplog.lock_or_err()?;
+            let with_state =
+                !state.has_uncommitted_changes() && &state.frontiers == oplog.frontiers();
+            let bytes = if with_state {
+                encode_snapshot(&oplog, &state)
+            } else {
+                oplog.export_from(&Default::default())
+            };
+            (bytes, with_state)
+        };
+
+        let fork = LoroDoc::new();
+        if with_state {
+            decode_app_snapshot(&fork, &bytes, true)?;
+        } else {
+            fork.import(&bytes)?;
+        }
+
+        Ok(fork)
+    }
+
+    /// Compute the diffs that turn the doc at version `from` into the doc at version `to`.
+    ///
+    /// `from` can be greater than `to`, then the diffs revert the changes in between.
+    /// The result is empty if the versions are equal. Both versions must be causally
+    /// closed, i.e. they include all the deps of the changes in them.
+    ///
+    /// The diffs are calculated from the oplog and applied to a copy of the state,
+    /// so this doc is not changed and its pending txn is not committed.
+    pub fn diff(
+        &self,
+        from: &VersionVector,
+        to: &VersionVector,
+    ) -> LoroResult<FxHashMap<ContainerID, Vec<Diff>>> {
+        let state = self.state.lock_or_recover();
+        let oplog = self.oplog.lock_or_err()?;
+        let from_frontiers = causally_closed_frontiers(&oplog, from)?;
+        let to_frontiers = causally_closed_frontiers(&oplog, to)?;
+        if from == to {
+            return Ok(FxHashMap::default());
+        }
+
+        // If the pending txn has changed the state, start from the empty state instead
+        let mut fork = state
+            .fork()
+            .unwrap_or_else(|| DocState::new(self.arena.clone()));
+        drop(state);
+        let mut calc = DiffCalculator::new();
+        let fork_vv = oplog.dag.frontiers_to_vv(&fork.frontiers).unwrap();
+        let diff = calc.calc_diff_internal(
+            &oplog,
+            &fork_vv,
+            Some(&fork.frontiers),
+            from,
+            Some(&from_frontiers),
+        );
+        fork.apply_diff(InternalDocDiff {
+            origin: "diff".into(),
+            local: true,
+            diff: Cow::Owned(diff),
+            from_checkout: true,
+            new_version: Cow::Owned(from_frontiers.clone()),
+        });
+
+        fork.start_recording();
+        let diff =
+            calc.calc_diff_internal(&oplog, from, Some(&from_frontiers), to, Some(&to_frontiers));
+        fork.apply_diff(InternalDocDiff {
+            origin: "diff".into(),
+            local: true,
+            diff: Cow::Owned(diff),
+            from_checkout: true,
+            new_version: Cow::Owned(to_frontiers),
+        });
+        let mut ans: FxHashMap<ContainerID, Vec<Diff>> = FxHashMap::default();
+        for event in fork.take_events() {
+            for container_diff in event.diff {
+                ans.entry(container_diff.id)
+                    .or_default()
+                    .push(container_diff.diff);
+            }
+        }
+
+        Ok(ans)
+    }
+
+    /// Import the data of a [ImportPreview] created by [LoroDoc::preview_import].
+    #[inline]
+    pub fn commit_preview(&self, preview: ImportPreview) -> LoroResult<()> {
+        self.import(&preview.bytes)
+    }
+}
+
+/// The result of [LoroDoc::preview_import].
+#[derive(Debug, Clone)]
+pub struct ImportPreview {
+    bytes: Vec<u8>,
+    /// The deep value of the doc after the import
+    pub value: LoroValue,
+    /// The diffs of the containers changed by the import
+    pub diff: Vec<ContainerDiff>,
 }
 
-fn parse_encode_header(bytes: &[u8]) -> Result<(&[u8], EncodeMode), LoroError> {
-    if bytes.len() <= 6 {
-        return Err(LoroError::DecodeError("Invalid import data".into()));
+/// Convert `vv` to frontiers, returning an error if it's unknown to `oplog`
+/// or if it's not causally closed.
+fn causally_closed_frontiers(oplog: &OpLog, vv: &VersionVector) -> LoroResult<Frontiers> {
+    if !oplog.vv().includes_vv(vv) {
+        return Err(LoroError::NotFoundError(
+            format!("Cannot find the specified version {:?}", vv).into_boxed_str(),
+        ));
     }
-    let (magic_bytes, input) = bytes.split_at(4);
-    let magic_bytes: [u8; 4] = magic_bytes.try_into().unwrap();
-    if magic_bytes != MAGIC_BYTES {
-        return Err(LoroError::DecodeError("Invalid header bytes".into()));
+
+    let frontiers = oplog.dag.vv_to_frontiers(vv);
+    let closed = oplog
+        .dag
+        .frontiers_to_vv(&frontiers)
+        .is_some_and(|closed| closed.includes_vv(vv) && vv.includes_vv(&closed));
+    if !closed {
+        return Err(LoroError::ArgErr(
+            format!("Version {:?} is not causally closed", vv).into_boxed_str(),
+        ));
+    }
+
+    Ok(frontiers)
+}
+
+fn is_empty_container_value(value: &LoroValue) -> bool {
+    match value {
+        LoroValue::String(s) => s.is_empty(),
+        LoroValue::List(l) => l.is_empty(),
+        LoroValue::Map(m) => m.is_empty(),
+        _ => false,
     }
-    let (version, input) = input.split_at(1);
-    if version != [ENCODE_SCHEMA_VERSION] {
-        return Err(LoroError::DecodeError("Invalid version".into()));
+}
+
+/// Hash the value with explicit tags and fixed-width lengths, so the hash is the same
+/// on every target, e.g. native and wasm peers. The entries of maps are hashed in key order.
+fn hash_value_canonical(value: &LoroValue, hasher: &mut FxHasher64) {
+    match value {
+        LoroValue::Null => hasher.write_u8(0),
+        LoroValue::Bool(b) => {
+            hasher.write_u8(1);
+            hasher.write_u8(*b as u8);
+        }
+        LoroValue::Double(d) => {
+            hasher.write_u8(2);
+            hasher.write_u64(d.to_bits());
+        }
+        LoroValue::I32(i) => {
+            hasher.write_u8(3);
+            hasher.write_i32(*i);
+        }
+        LoroValue::Binary(b) => {
+            hasher.write_u8(4);
+            hash_bytes(b, hasher);
+        }
+        LoroValue::String(s) => {
+            hasher.write_u8(5);
+            hash_bytes(s.as_bytes(), hasher);
+        }
+        LoroValue::List(list) => {
+            hasher.write_u8(6);
+            hasher.write_u64(list.len() as u64);
+            for v in list.iter() {
+                hash_value_canonical(v, hasher);
+            }
+        }
+        LoroValue::Map(map) => {
+            hasher.write_u8(7);
+            hasher.write_u64(map.len() as u64);
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            for (k, v) in entries {
+                hash_bytes(k.as_bytes(), hasher);
+                hash_value_canonical(v, hasher);
+            }
+        }
+        LoroValue::Container(ContainerID::Root {
+            name,
+            container_type,
+        }) => {
+            hasher.write_u8(8);
+            hash_bytes(name.as_bytes(), hasher);
+            hasher.write_u8(container_type.to_u8());
+        }
+        LoroValue::Container(ContainerID::Normal {
+            peer,
+            counter,
+            container_type,
+        }) => {
+            hasher.write_u8(9);
+            hasher.write_u64(*peer);
+            hasher.write_i32(*counter);
+            hasher.write_u8(container_type.to_u8());
+        }
     }
-    let mode: EncodeMode = input[0].try_into()?;
-    Ok((&input[1..], mode))
+}
+
+fn hash_bytes(bytes: &[u8], hasher: &mut FxHasher64) {
+    hasher.write_u64(bytes.len() as u64);
+    hasher.write(bytes);
 }
 
 #[cfg(test)]
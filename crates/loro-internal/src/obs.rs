@@ -10,18 +10,36 @@ use crate::container::idx::ContainerIdx;
 
 use super::{
     arena::SharedArena,
-    event::{DiffEvent, DocDiff},
+    event::{DiffEvent, DocDiff, DocSignal, SignalEvent},
 };
 
 pub type Subscriber = Arc<dyn (for<'a> Fn(DiffEvent<'a>)) + Send + Sync>;
+pub type SignalSubscriber = Arc<dyn for<'a> Fn(SignalEvent<'a>) + Send + Sync>;
+
+/// How a subscriber is notified about the changes.
+#[derive(Clone)]
+pub enum NotifyMode {
+    /// Receive [DiffEvent]s that contain the deltas of the changed containers.
+    Delta(Subscriber),
+    /// Only receive [SignalEvent]s that tell which container has changed and the new version.
+    ///
+    /// It's for the subscribers that read the state again anyway. If all the
+    /// subscribers of a doc are in this mode, the deltas are never built.
+    Signal(SignalSubscriber),
+}
+
+enum QueuedEvent {
+    Diff(DocDiff),
+    Signal(DocSignal),
+}
 
 #[derive(Default)]
 struct ObserverInner {
-    subscribers: FxHashMap<SubID, Subscriber>,
+    subscribers: FxHashMap<SubID, NotifyMode>,
     containers: FxHashMap<ContainerIdx, FxHashSet<SubID>>,
     root: FxHashSet<SubID>,
     deleted: FxHashSet<SubID>,
-    event_queue: Vec<DocDiff>,
+    event_queue: Vec<QueuedEvent>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -61,18 +79,26 @@ impl Observer {
     }
 
     pub fn subscribe(&self, id: &ContainerID, callback: Subscriber) -> SubID {
+        self.subscribe_with_mode(id, NotifyMode::Delta(callback))
+    }
+
+    pub fn subscribe_with_mode(&self, id: &ContainerID, mode: NotifyMode) -> SubID {
         let idx = self.arena.register_container(id);
         let sub_id = self.fetch_add_next_id();
         let mut inner = self.inner.lock().unwrap();
-        inner.subscribers.insert(sub_id, callback);
+        inner.subscribers.insert(sub_id, mode);
         inner.containers.entry(idx).or_default().insert(sub_id);
         sub_id
     }
 
     pub fn subscribe_root(&self, callback: Subscriber) -> SubID {
+        self.subscribe_root_with_mode(NotifyMode::Delta(callback))
+    }
+
+    pub fn subscribe_root_with_mode(&self, mode: NotifyMode) -> SubID {
         let sub_id = self.fetch_add_next_id();
         let mut inner = self.inner.lock().unwrap();
-        inner.subscribers.insert(sub_id, callback);
+        inner.subscribers.insert(sub_id, mode);
         inner.root.insert(sub_id);
         sub_id
     }
@@ -85,50 +111,91 @@ impl Observer {
     }
 
     pub(crate) fn emit(&self, doc_diff: DocDiff) {
+        self.emit_event(QueuedEvent::Diff(doc_diff));
+    }
+
+    pub(crate) fn emit_signal(&self, signal: DocSignal) {
+        self.emit_event(QueuedEvent::Signal(signal));
+    }
+
+    fn emit_event(&self, event: QueuedEvent) {
         if self.taken_times.load(Ordering::Relaxed) > 0 {
-            self.inner.lock().unwrap().event_queue.push(doc_diff);
+            self.inner.lock().unwrap().event_queue.push(event);
             return;
         }
 
         let mut inner = self.take_inner();
-        self.emit_inner(&doc_diff, &mut inner);
+        self.emit_inner(&event, &mut inner);
         self.reset_inner(inner);
     }
 
     // When emitting changes, we need to make sure that the observer is not locked.
-    fn emit_inner(&self, doc_diff: &DocDiff, inner: &mut ObserverInner) {
-        for container_diff in doc_diff.diff.iter() {
-            self.arena
-                .with_ancestors(container_diff.idx, |ancestor, is_self| {
-                    if let Some(subs) = inner.containers.get_mut(&ancestor) {
-                        subs.retain(|sub| match inner.subscribers.get_mut(sub) {
-                            Some(f) => {
-                                f(DiffEvent {
-                                    from_children: !is_self,
-                                    container: container_diff,
-                                    doc: doc_diff,
-                                });
-                                true
-                            }
-                            None => false,
-                        });
-                    }
-                });
+    fn emit_inner(&self, event: &QueuedEvent, inner: &mut ObserverInner) {
+        match event {
+            QueuedEvent::Diff(doc_diff) => {
+                for container_diff in doc_diff.diff.iter() {
+                    self.notify(
+                        inner,
+                        container_diff.idx,
+                        |mode, from_children| match mode {
+                            NotifyMode::Delta(f) => f(DiffEvent {
+                                from_children,
+                                container: container_diff,
+                                doc: doc_diff,
+                            }),
+                            NotifyMode::Signal(f) => f(SignalEvent {
+                                from_children,
+                                container: &container_diff.id,
+                                version: &doc_diff.to,
+                            }),
+                        },
+                    );
+                }
+            }
+            QueuedEvent::Signal(signal) => {
+                for &idx in signal.containers.iter() {
+                    let id = self.arena.get_container_id(idx).unwrap();
+                    self.notify(inner, idx, |mode, from_children| {
+                        // Signals are only recorded when there is no subscriber in delta mode
+                        if let NotifyMode::Signal(f) = mode {
+                            f(SignalEvent {
+                                from_children,
+                                container: &id,
+                                version: &signal.to,
+                            })
+                        }
+                    });
+                }
+            }
+        }
+    }
 
-            inner
-                .root
-                .retain(|sub| match inner.subscribers.get_mut(sub) {
-                    Some(f) => {
-                        f(DiffEvent {
-                            from_children: true,
-                            container: container_diff,
-                            doc: doc_diff,
-                        });
+    /// Call `f` on the subscribers of the container, its ancestors and the root
+    fn notify(
+        &self,
+        inner: &mut ObserverInner,
+        idx: ContainerIdx,
+        mut f: impl FnMut(&NotifyMode, bool),
+    ) {
+        self.arena.with_ancestors(idx, |ancestor, is_self| {
+            if let Some(subs) = inner.containers.get_mut(&ancestor) {
+                subs.retain(|sub| match inner.subscribers.get(sub) {
+                    Some(mode) => {
+                        f(mode, !is_self);
                         true
                     }
                     None => false,
                 });
-        }
+            }
+        });
+
+        inner.root.retain(|sub| match inner.subscribers.get(sub) {
+            Some(mode) => {
+                f(mode, true);
+                true
+            }
+            None => false,
+        });
     }
 
     fn take_inner(&self) -> ObserverInner {
@@ -216,6 +283,8 @@ impl Observer {
 #[cfg(test)]
 mod test {
 
+    use loro_common::{ContainerType, ID};
+
     use crate::loro::LoroDoc;
 
     use super::*;
@@ -278,4 +347,121 @@ mod test {
         }
         assert_eq!(count.load(Ordering::SeqCst), 2);
     }
+
+    #[test]
+    fn subscribe_container() {
+        let loro = LoroDoc::new_auto_commit();
+        let map = loro.get_map("map");
+        let list = map
+            .insert_container_("list", ContainerType::List)
+            .unwrap()
+            .into_list()
+            .unwrap();
+        let text = loro.get_text("text");
+        loro.commit_then_renew();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_cp = Arc::clone(&events);
+        let sub = loro.subscribe(
+            &map.id(),
+            Arc::new(move |event| {
+                events_cp
+                    .lock()
+                    .unwrap()
+                    .push((event.container.id.clone(), event.from_children));
+            }),
+        );
+
+        text.insert_(0, "123").unwrap();
+        loro.commit_then_renew();
+        assert!(events.lock().unwrap().is_empty());
+
+        list.insert_(0, 1.into()).unwrap();
+        loro.commit_then_renew();
+        map.insert_("key", 1.into()).unwrap();
+        loro.commit_then_renew();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![(list.id(), true), (map.id(), false)]
+        );
+
+        loro.unsubscribe(sub);
+        map.insert_("key", 2.into()).unwrap();
+        loro.commit_then_renew();
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn with_txn_emits_one_event() {
+        let loro = LoroDoc::new();
+        let text = loro.get_text("text");
+        let map = loro.get_map("map");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_cp = Arc::clone(&events);
+        loro.subscribe_root(Arc::new(move |event| {
+            events_cp
+                .lock()
+                .unwrap()
+                .push((event.container.id.clone(), event.doc.diff.len()));
+        }));
+        loro.with_txn(|txn| {
+            for i in 0..10 {
+                text.insert(txn, i, "a")?;
+                map.insert(txn, "key", (i as i32).into())?;
+            }
+            text.delete(txn, 0, 5)
+        })
+        .unwrap();
+        // the root subscriber is called once for each changed container of the same event
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.contains(&(text.id(), 2)));
+        assert!(events.contains(&(map.id(), 2)));
+        assert_eq!(text.get_value().as_string().unwrap().as_str(), "aaaaa");
+    }
+
+    #[test]
+    fn signal_mode() {
+        let loro = LoroDoc::new();
+        loro.set_peer_id(1).unwrap();
+        let text = loro.get_text("text");
+        let signals = Arc::new(Mutex::new(Vec::new()));
+        let signals_cp = Arc::clone(&signals);
+        loro.subscribe_with_mode(
+            &text.id(),
+            NotifyMode::Signal(Arc::new(move |event| {
+                signals_cp
+                    .lock()
+                    .unwrap()
+                    .push((event.container.clone(), event.version.clone()));
+            })),
+        );
+        assert!(!loro.app_state().lock().unwrap().is_recording());
+
+        loro.with_txn(|txn| text.insert(txn, 0, "123")).unwrap();
+        let other = LoroDoc::new();
+        other.set_peer_id(2).unwrap();
+        other
+            .with_txn(|txn| other.get_text("text").insert(txn, 0, "abc"))
+            .unwrap();
+        loro.import(&other.export_from(&Default::default()))
+            .unwrap();
+        assert_eq!(
+            *signals.lock().unwrap(),
+            vec![
+                (text.id(), ID::new(1, 2).into()),
+                (text.id(), loro.oplog_frontiers()),
+            ]
+        );
+        assert!(!loro.app_state().lock().unwrap().is_recording());
+
+        // signal subscribers are still notified when the deltas are recorded for others
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_cp = Arc::clone(&count);
+        loro.subscribe_root(Arc::new(move |_| {
+            count_cp.fetch_add(1, Ordering::SeqCst);
+        }));
+        loro.with_txn(|txn| text.insert(txn, 0, "456")).unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(signals.lock().unwrap().len(), 3);
+    }
 }
@@ -1,6 +1,8 @@
 pub(crate) mod dag;
 mod pending_changes;
 
+pub use pending_changes::ImportReport;
+
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
@@ -9,24 +11,30 @@ use std::rc::Rc;
 use std::sync::Mutex;
 
 use fxhash::FxHashMap;
+use loro_common::{ContainerID, ContainerType, IdSpan};
 use rle::{HasLength, RleCollection, RlePush, RleVec, Sliceable};
 use smallvec::SmallVec;
 // use tabled::measurment::Percent;
 
 use crate::change::{Change, Lamport, Timestamp};
+use crate::configure::Configure;
+use crate::container::idx::ContainerIdx;
 use crate::container::list::list_op;
 use crate::dag::DagUtils;
 use crate::diff_calc::tree::MoveLamportAndID;
-use crate::diff_calc::TreeDiffCache;
+use crate::diff_calc::{RichtextTrackerCache, TreeDiffCache};
 use crate::encoding::RemoteClientChanges;
-use crate::encoding::{decode_oplog, encode_oplog, EncodeMode};
+use crate::encoding::{decode_oplog, encode_changes, encode_oplog, EncodeMode};
 use crate::id::{Counter, PeerID, ID};
-use crate::op::{ListSlice, RawOpContent, RemoteOp};
+use crate::op::{InnerContent, ListSlice, Op, RawOpContent, RemoteOp, RichOp};
 use crate::span::{HasCounterSpan, HasIdSpan, HasLamportSpan};
+use crate::utils::lock::LockOrRecover;
 use crate::version::{Frontiers, ImVersionVector, VersionVector};
 use crate::LoroError;
 
 type ClientChanges = FxHashMap<PeerID, Vec<Change>>;
+pub type OnLocalOpsFn = Box<dyn Fn(&[Op], &Change) + Send + Sync>;
+pub type OpObserverFn = Box<dyn FnMut(&RichOp, &ContainerID) + Send + Sync>;
 use self::pending_changes::PendingChanges;
 
 use super::arena::SharedArena;
@@ -54,6 +62,17 @@ pub struct OpLog {
     pub(crate) batch_importing: bool,
 
     pub(crate) tree_parent_cache: Mutex<TreeDiffCache>,
+    /// The cached trackers of text containers, used to resolve the ids of their elements
+    pub(crate) richtext_tracker_cache: Mutex<RichtextTrackerCache>,
+    pub(crate) configure: Configure,
+    on_local_ops: Option<OnLocalOpsFn>,
+    op_observer: Option<OpObserverFn>,
+    /// The spans of the new changes that haven't been passed to the `op_observer`.
+    /// The arena may be locked when a change is inserted, so the observer is invoked
+    /// afterwards by [OpLog::notify_op_observer].
+    unobserved_spans: Vec<IdSpan>,
+    /// The spans of the decoded changes, only recorded by [OpLog::import_with_report]
+    incoming_spans: Option<Vec<IdSpan>>,
 }
 
 /// [AppDag] maintains the causal graph of the app.
@@ -87,6 +106,12 @@ impl Clone for OpLog {
             pending_changes: Default::default(),
             batch_importing: false,
             tree_parent_cache: Default::default(),
+            richtext_tracker_cache: Default::default(),
+            configure: self.configure.clone(),
+            on_local_ops: None,
+            op_observer: None,
+            unobserved_spans: Vec::new(),
+            incoming_spans: None,
         }
     }
 }
@@ -161,6 +186,12 @@ impl OpLog {
             pending_changes: Default::default(),
             batch_importing: false,
             tree_parent_cache: Default::default(),
+            richtext_tracker_cache: Default::default(),
+            configure: Default::default(),
+            on_local_ops: None,
+            op_observer: None,
+            unobserved_spans: Vec::new(),
+            incoming_spans: None,
         }
     }
 
@@ -196,18 +227,69 @@ impl OpLog {
         self.dag.map.is_empty() && self.arena.can_import_snapshot()
     }
 
+    /// Get all the changes, grouped by peer and sorted by counter.
+    ///
+    /// Changes are never dropped, even after every peer has seen them: the changes of
+    /// each peer must be contiguous from counter 0, and importing a concurrent change
+    /// or exporting to a peer replays them from the common ancestor. See the note on
+    /// [crate::LoroDoc::export_snapshot] for shipping the content without the history.
     pub fn changes(&self) -> &ClientChanges {
         &self.changes
     }
 
+    /// Set the callback that is invoked with the ops of every new local change,
+    /// right after the change is committed and before any event is emitted.
+    ///
+    /// It's a low-level tap for custom transports to forward local ops without
+    /// polling [Self::export_from]. It replaces the previous callback.
+    ///
+    /// The callback is invoked while the oplog is locked, so it must not access the doc.
+    pub fn on_local_ops(&mut self, callback: impl Fn(&[Op], &Change) + Send + Sync + 'static) {
+        self.on_local_ops = Some(Box::new(callback));
+    }
+
+    /// Set the observer that is invoked with every op added to the oplog, both local
+    /// and remote, e.g. to maintain a derived read model. It replaces the previous one.
+    ///
+    /// Ops are observed in the order they are added, which is a causal order. Remote
+    /// ops whose deps are missing are only observed once they are applied. Unlike
+    /// events, ops on detached containers and concurrent ops that lose the conflict
+    /// are observed as well.
+    ///
+    /// The observer is invoked while the oplog is locked, so it must not access the doc.
+    pub fn set_op_observer(
+        &mut self,
+        observer: impl FnMut(&RichOp, &ContainerID) + Send + Sync + 'static,
+    ) {
+        self.op_observer = Some(Box::new(observer));
+    }
+
     /// This is the only place to update the `OpLog.changes`
-    pub(crate) fn insert_new_change(&mut self, mut change: Change, _: EnsureChangeDepsAreAtTheEnd) {
+    ///
+    /// Only a change committed by a local txn (`from_txn`) may be merged into the
+    /// previous change of the same peer. Imported changes are kept as they are, so
+    /// every peer stores the same change boundaries.
+    pub(crate) fn insert_new_change(
+        &mut self,
+        mut change: Change,
+        _: EnsureChangeDepsAreAtTheEnd,
+        from_txn: bool,
+    ) {
+        if self.op_observer.is_some() {
+            self.unobserved_spans.push(change.id_span());
+        }
+
+        let merge_local_ops = from_txn && self.configure.merge_local_ops;
         let entry = self.changes.entry(change.id.peer).or_default();
         match entry.last_mut() {
             Some(last) => {
                 assert_eq!(change.id.counter, last.ctr_end());
                 let timestamp_change = change.timestamp - last.timestamp;
-                if !last.has_dependents && change.deps_on_self() && timestamp_change < 1000 {
+                if merge_local_ops
+                    && !last.has_dependents
+                    && change.deps_on_self()
+                    && timestamp_change < 1000
+                {
                     for op in take(change.ops.vec_mut()) {
                         last.ops.push(op);
                     }
@@ -261,7 +343,7 @@ impl OpLog {
         let mark = self.insert_dag_node_on_new_change(&change);
 
         // Update tree cache
-        let mut tree_cache = self.tree_parent_cache.lock().unwrap();
+        let mut tree_cache = self.tree_parent_cache.lock_or_recover();
         for op in change.ops().iter() {
             if let crate::op::InnerContent::Tree(tree) = op.content {
                 let diff = op.counter - change.id.counter;
@@ -284,10 +366,49 @@ impl OpLog {
         }
 
         drop(tree_cache);
-        self.insert_new_change(change, mark);
+        if from_txn {
+            if let Some(callback) = &self.on_local_ops {
+                callback(&change.ops, &change);
+            }
+        }
+
+        self.insert_new_change(change, mark, from_txn);
+        self.notify_op_observer();
         Ok(())
     }
 
+    /// Invoke the `op_observer` with the ops of the changes inserted since the last call.
+    ///
+    /// It must be called after the changes are inserted, when the arena isn't locked.
+    pub(crate) fn notify_op_observer(&mut self) {
+        let Some(observer) = &mut self.op_observer else {
+            return;
+        };
+
+        for span in take(&mut self.unobserved_spans) {
+            // the change may have been merged into the previous change of the peer
+            let changes = &self.changes[&span.client_id];
+            let change = changes
+                .get_by_atom_index(span.counter.start)
+                .unwrap()
+                .element;
+            for op in change.ops.iter() {
+                if op.counter >= span.counter.end || op.ctr_end() <= span.counter.start {
+                    continue;
+                }
+
+                let op = RichOp::new_by_slice_on_change(
+                    change,
+                    span.counter.start - change.id.counter,
+                    span.counter.end - change.id.counter,
+                    op,
+                );
+                let container = self.arena.get_container_id(op.op.container).unwrap();
+                observer(&op, &container);
+            }
+        }
+    }
+
     /// Every time we import a new change, it should run this function to update the dag
     pub(crate) fn insert_dag_node_on_new_change(
         &mut self,
@@ -399,6 +520,13 @@ impl OpLog {
         Ok(())
     }
 
+    /// The lamport that the next change after the current frontiers would get.
+    ///
+    /// It cannot be seeded. The lamport of a change is always derived from its deps,
+    /// and the peers recalculate it on import, so a preset baseline would make them
+    /// disagree. Fixtures with the same peer ids and edits get the same lamports anyway.
+    /// For deterministic timestamps, set [Configure::get_time] or use
+    /// [crate::txn::Transaction::set_timestamp].
     pub fn next_lamport(&self) -> Lamport {
         self.next_lamport
     }
@@ -408,10 +536,28 @@ impl OpLog {
         ID::new(peer, cnt)
     }
 
+    /// Get the changes of `peer` sorted by counter.
     pub fn get_peer_changes(&self, peer: PeerID) -> Option<&Vec<Change>> {
         self.changes.get(&peer)
     }
 
+    /// Iterate over the ops of `peer` in counter order. It's empty if the peer is unknown.
+    ///
+    /// The iterator is double ended, so the ops can be scanned backward, e.g. to find
+    /// the last op of the peer on a container. It's not an [ExactSizeIterator], because
+    /// the changes only store their atom lengths and adjacent ops are merged.
+    pub fn iter_peer_ops(&self, peer: PeerID) -> impl DoubleEndedIterator<Item = RichOp<'_>> {
+        self.changes.get(&peer).into_iter().flat_map(|changes| {
+            changes.iter().flat_map(|change| {
+                change
+                    .ops
+                    .vec()
+                    .iter()
+                    .map(move |op| RichOp::new_by_change(change, op))
+            })
+        })
+    }
+
     pub(crate) fn vv(&self) -> &VersionVector {
         &self.dag.vv
     }
@@ -428,8 +574,17 @@ impl OpLog {
     }
 
     pub(crate) fn export_changes_from(&self, from: &VersionVector) -> RemoteClientChanges {
+        self.export_changes_between(from, self.vv())
+    }
+
+    /// Export the changes that are included in `to` but not in `from`
+    pub(crate) fn export_changes_between(
+        &self,
+        from: &VersionVector,
+        to: &VersionVector,
+    ) -> RemoteClientChanges<'_> {
         let mut changes = RemoteClientChanges::default();
-        for (&peer, &cnt) in self.vv().iter() {
+        for (&peer, &cnt) in to.iter() {
             let start_cnt = from.get(&peer).copied().unwrap_or(0);
             if cnt <= start_cnt {
                 continue;
@@ -439,13 +594,19 @@ impl OpLog {
             if let Some(peer_changes) = self.changes.get(&peer) {
                 if let Some(result) = peer_changes.get_by_atom_index(start_cnt) {
                     for change in &peer_changes[result.merged_index..] {
-                        if change.id.counter < start_cnt {
-                            if change.id.counter + change.atom_len() as Counter <= start_cnt {
-                                continue;
-                            }
+                        if change.id.counter >= cnt {
+                            break;
+                        }
+
+                        if change.id.counter + change.atom_len() as Counter <= start_cnt {
+                            continue;
+                        }
 
-                            let sliced = change
-                                .slice((start_cnt - change.id.counter) as usize, change.atom_len());
+                        if change.id.counter < start_cnt || change.ctr_end() > cnt {
+                            let sliced = change.slice(
+                                (start_cnt.max(change.id.counter) - change.id.counter) as usize,
+                                (cnt.min(change.ctr_end()) - change.id.counter) as usize,
+                            );
                             temp.push(self.convert_change_to_remote(&sliced));
                         } else {
                             temp.push(self.convert_change_to_remote(change));
@@ -475,6 +636,16 @@ impl OpLog {
             .unwrap_or(Lamport::MAX)
     }
 
+    /// Get the root container with the given name and type, registering it if needed.
+    ///
+    /// The id of a root container is derived from its name and type, so calling this
+    /// twice, or on different peers, always gives the same container.
+    pub fn get_or_create_root(&self, name: &str, ty: ContainerType) -> (ContainerID, ContainerIdx) {
+        let id = ContainerID::new_root(name, ty);
+        let idx = self.arena.register_container(&id);
+        (id, idx)
+    }
+
     pub fn get_change_at(&self, id: ID) -> Option<&Change> {
         if let Some(peer_changes) = self.changes.get(&id.peer) {
             if let Some(result) = peer_changes.get_by_atom_index(id.counter) {
@@ -490,11 +661,130 @@ impl OpLog {
         Some(self.convert_change_to_remote(change))
     }
 
+    /// Iterate over all the changes in a deterministic causal order.
+    ///
+    /// Changes are sorted by `(lamport, peer)`. A change's lamport is always greater than
+    /// the lamports of its deps, so every change is yielded after all of its deps, and
+    /// concurrent changes are ordered by lamport first and then by peer id. The order
+    /// only depends on the changes themselves, so every peer with the same history
+    /// gets the same order.
+    ///
+    /// It can be reversed to visit every change before its deps.
+    pub fn iter_changes_causal(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &Change> + ExactSizeIterator {
+        let mut changes: Vec<&Change> = self.changes.values().flatten().collect();
+        changes.sort_unstable_by_key(|c| (c.lamport, c.id.peer));
+        changes.into_iter()
+    }
+
+    /// Get the spans of the ops concurrent to the op `id`, i.e. the ops that are
+    /// neither in the causal past of `id` nor have `id` in their causal past.
+    ///
+    /// There is at most one span per change, because only a prefix of a change can be
+    /// in the causal past of `id`, e.g. when the change was merged from several local
+    /// txns. The ops of the same peer are never concurrent to `id`. The result is in the
+    /// order of [OpLog::iter_changes_causal], and is empty if `id` is unknown.
+    pub fn concurrent_changes(&self, id: ID) -> Vec<IdSpan> {
+        let Some(vv) = self.dag.get_vv(id) else {
+            return Vec::new();
+        };
+
+        let mut ans = Vec::new();
+        for change in self.iter_changes_causal() {
+            let peer = change.id.peer;
+            if peer == id.peer {
+                continue;
+            }
+
+            let start = change.id.counter.max(vv.get(&peer).copied().unwrap_or(0));
+            if start >= change.ctr_end() {
+                continue;
+            }
+
+            // the ops of a change share the same deps, so either all or none of the
+            // ops after `start` have `id` in their causal past
+            let start_vv = self.dag.get_vv(ID::new(peer, start)).unwrap();
+            if !start_vv.includes_id(id) {
+                ans.push(IdSpan::new(peer, start, change.ctr_end()));
+            }
+        }
+
+        ans
+    }
+
+    /// Iterate over the ops of `container` with their ids, in the order of
+    /// [OpLog::iter_changes_causal]. It's empty if the container is unknown.
+    ///
+    /// The content of text inserts refers to the text arena of this doc, use
+    /// [OpLog::get_remote_change_at] to get self-contained ops.
+    pub fn ops_for_container(
+        &self,
+        container: &ContainerID,
+    ) -> impl Iterator<Item = (ID, &InnerContent)> + '_ {
+        let idx = self.arena.id_to_idx(container);
+        self.iter_changes_causal()
+            .filter(move |_| idx.is_some())
+            .flat_map(move |change| {
+                change
+                    .ops
+                    .iter()
+                    .filter(move |op| Some(op.container) == idx)
+                    .map(move |op| (ID::new(change.id.peer, op.counter), &op.content))
+            })
+    }
+
+    /// Get the number of ops ever applied to `container`, including the deleted ones.
+    ///
+    /// An op inserting `n` elements counts as `n` ops, the same as the counters it takes.
+    /// It walks all the changes, so it's meant for on-demand metrics.
+    pub fn container_op_count(&self, container: &ContainerID) -> usize {
+        let Some(idx) = self.arena.id_to_idx(container) else {
+            return 0;
+        };
+
+        self.changes
+            .values()
+            .flatten()
+            .flat_map(|change| change.ops.iter())
+            .filter(|op| op.container == idx)
+            .map(|op| op.atom_len())
+            .sum()
+    }
+
+    /// Get the number of changes that contain ops on `container`.
+    ///
+    /// Consecutive local changes may be merged into one change, so it's only an
+    /// approximation of the number of commits.
+    pub fn container_change_count(&self, container: &ContainerID) -> usize {
+        let Some(idx) = self.arena.id_to_idx(container) else {
+            return 0;
+        };
+
+        self.changes
+            .values()
+            .flatten()
+            .filter(|change| change.ops.iter().any(|op| op.container == idx))
+            .count()
+    }
+
+    /// Get the changes whose own timestamp is greater than `timestamp`, in causal order.
+    ///
+    /// Timestamps are not causally ordered, so the result may skip a change whose
+    /// dependents are included. Consecutive local changes may be merged into one
+    /// change that keeps the timestamp of the first one.
+    pub fn changes_since_timestamp(&self, timestamp: Timestamp) -> Vec<Change<RemoteOp<'_>>> {
+        self.iter_causally(VersionVector::new(), self.vv().clone())
+            .filter(|(change, _)| change.timestamp > timestamp)
+            .map(|(change, _)| self.convert_change_to_remote(change))
+            .collect()
+    }
+
     fn convert_change_to_remote(&self, change: &Change) -> Change<RemoteOp> {
         let mut ops = RleVec::new();
         for op in change.ops.iter() {
             for op in self.local_op_to_remote(op) {
-                ops.push(op);
+                push_coalesced(&mut ops, op);
             }
         }
 
@@ -608,13 +898,19 @@ impl OpLog {
     ) -> Result<(), LoroError> {
         // check whether we can append the new changes
         self.check_changes(&remote_changes)?;
+        self.record_incoming(remote_changes.values().flatten());
         let latest_vv = self.dag.vv.clone();
         // op_converter is faster than using arena directly
         let ids = self.arena.clone().with_op_converter(|converter| {
-            self.apply_appliable_changes_and_cache_pending(remote_changes, converter, latest_vv)
+            self.apply_appliable_changes_and_cache_pending(
+                remote_changes.into_values().flatten(),
+                converter,
+                latest_vv,
+            )
         });
         let mut latest_vv = self.dag.vv.clone();
         self.try_apply_pending(ids, &mut latest_vv);
+        self.notify_op_observer();
         if !self.batch_importing {
             self.dag.refresh_frontiers();
         }
@@ -659,6 +955,62 @@ impl OpLog {
         encode_oplog(self, vv, EncodeMode::Auto)
     }
 
+    /// Export the changes needed to bring a peer at `from` to the version `target`.
+    ///
+    /// Only the causal past of `target` is exported, so the changes after `target`
+    /// are excluded even though they exist locally.
+    pub fn export_to_frontier(
+        &self,
+        from: &VersionVector,
+        target: &Frontiers,
+    ) -> Result<Vec<u8>, LoroError> {
+        let Some(to) = self.dag.frontiers_to_vv(target) else {
+            return Err(LoroError::NotFoundError(
+                format!("Cannot find the specified version {:?}", target).into_boxed_str(),
+            ));
+        };
+
+        Ok(encode_changes(self.export_changes_between(from, &to)))
+    }
+
+    /// Export the changes after `from` in chunks of about `chunk_size` atom ops.
+    ///
+    /// The changes are visited in causal order and only one chunk is encoded at
+    /// a time, so a large history can be sent without building one huge buffer.
+    /// Every chunk carries its own header and can be imported on its own, as
+    /// long as the chunks before it have been imported.
+    pub fn export_stream<'a>(
+        &'a self,
+        from: &VersionVector,
+        chunk_size: usize,
+    ) -> impl Iterator<Item = Vec<u8>> + 'a {
+        let mut iter = self.iter_causally(from.clone(), self.vv().clone());
+        let from = from.clone();
+        std::iter::from_fn(move || {
+            let mut changes = RemoteClientChanges::default();
+            let mut len = 0;
+            while len < chunk_size.max(1) {
+                let Some((change, _)) = iter.next() else {
+                    break;
+                };
+
+                let start_cnt = from.get(&change.id.peer).copied().unwrap_or(0);
+                let change = if change.id.counter < start_cnt {
+                    let sliced =
+                        change.slice((start_cnt - change.id.counter) as usize, change.atom_len());
+                    self.convert_change_to_remote(&sliced)
+                } else {
+                    self.convert_change_to_remote(change)
+                };
+
+                len += change.atom_len();
+                changes.entry(change.id.peer).or_default().push(change);
+            }
+
+            (!changes.is_empty()).then(|| encode_changes(changes))
+        })
+    }
+
     #[inline(always)]
     pub fn decode(&mut self, data: &[u8]) -> Result<(), LoroError> {
         decode_oplog(self, data)
@@ -873,3 +1225,512 @@ impl Default for OpLog {
         Self::new()
     }
 }
+
+/// Push the op to the exported ops. A run of inserts that are contiguous in the same
+/// container is coalesced into a single insert, so the wire form has fewer ops.
+///
+/// Local ops are merged only if their content is contiguous in the arena,
+/// but the exported content is owned, so it doesn't have this limit.
+fn push_coalesced<'a>(ops: &mut RleVec<[RemoteOp<'a>; 1]>, op: RemoteOp<'a>) {
+    if let Some(last) = ops.vec_mut().last_mut() {
+        if last.container == op.container && last.counter + last.atom_len() as Counter == op.counter
+        {
+            if let (
+                RawOpContent::List(list_op::ListOp::Insert { slice, pos }),
+                RawOpContent::List(list_op::ListOp::Insert {
+                    slice: other_slice,
+                    pos: other_pos,
+                }),
+            ) = (&mut last.content, &op.content)
+            {
+                if *pos + slice.atom_len() == *other_pos {
+                    match (slice, other_slice) {
+                        (
+                            ListSlice::RawStr { str, unicode_len },
+                            ListSlice::RawStr {
+                                str: other_str,
+                                unicode_len: other_len,
+                            },
+                        ) => {
+                            str.to_mut().push_str(other_str);
+                            *unicode_len += other_len;
+                            return;
+                        }
+                        (ListSlice::RawData(values), ListSlice::RawData(other_values)) => {
+                            values.to_mut().extend_from_slice(other_values);
+                            return;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    ops.push(op);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        container::list::list_op::InnerListOp, op::InnerContent, span::HasId, LoroDoc, ToJson,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn coalesce_inserts_on_export() {
+        let mut oplog = OpLog::new();
+        let idx = oplog
+            .arena
+            .register_container(&ContainerID::new_root("text", ContainerType::Text));
+        let insert = |oplog: &OpLog, s: &str, pos: u32, counter: Counter| {
+            let (slice, unicode_start) = oplog.arena.alloc_str_with_slice(s);
+            Op::new(
+                ID::new(1, counter),
+                InnerContent::List(InnerListOp::InsertText {
+                    slice,
+                    unicode_start: unicode_start as u32,
+                    unicode_len: s.chars().count() as u32,
+                    pos,
+                }),
+                idx,
+            )
+        };
+
+        let mut ops = RleVec::new();
+        ops.push(insert(&oplog, "ab", 0, 0));
+        // the contents are not contiguous in the arena, so the local ops are not merged
+        oplog.arena.alloc_str_with_slice("gap");
+        ops.push(insert(&oplog, "c", 2, 2));
+        assert_eq!(ops.len(), 2);
+        let change = Change {
+            ops,
+            deps: Frontiers::default(),
+            id: ID::new(1, 0),
+            lamport: 0,
+            timestamp: 0,
+            has_dependents: false,
+        };
+        oplog.import_local_change(change, false).unwrap();
+
+        let remote = oplog.get_remote_change_at(ID::new(1, 0)).unwrap();
+        assert_eq!(remote.ops.len(), 1);
+        let RawOpContent::List(list_op::ListOp::Insert {
+            slice: ListSlice::RawStr { str, unicode_len },
+            pos: 0,
+        }) = &remote.ops[0].content
+        else {
+            unreachable!()
+        };
+        assert_eq!((&**str, *unicode_len), ("abc", 3));
+
+        let doc = LoroDoc::new();
+        doc.import(&oplog.export_from(&Default::default())).unwrap();
+        assert_eq!(
+            doc.get_deep_value().to_json_value(),
+            serde_json::json!({"text": "abc"})
+        );
+    }
+
+    #[test]
+    fn get_or_create_root_is_idempotent() {
+        let oplog = OpLog::new();
+        let (id, idx) = oplog.get_or_create_root("main", ContainerType::Text);
+        assert_eq!(id, ContainerID::new_root("main", ContainerType::Text));
+        // another store derives the same id
+        assert_eq!(
+            OpLog::new()
+                .get_or_create_root("main", ContainerType::Text)
+                .0,
+            id
+        );
+        assert_eq!(
+            oplog.get_or_create_root("main", ContainerType::Text),
+            (id, idx)
+        );
+        let (_, other) = oplog.get_or_create_root("main", ContainerType::List);
+        assert_ne!(idx, other);
+
+        // two peers creating "main" independently get the same container and converge
+        let a = LoroDoc::new_auto_commit();
+        let b = LoroDoc::new_auto_commit();
+        a.get_text("main").insert_(0, "a").unwrap();
+        b.get_text("main").insert_(0, "b").unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        b.import(&a.export_from(&b.oplog_vv())).unwrap();
+        assert_eq!(a.get_deep_value(), b.get_deep_value());
+        assert_eq!(a.get_text("main").len_unicode(), 2);
+    }
+
+    #[test]
+    fn concurrent_changes() {
+        // 1 --> 2 --> 1
+        //  \--> 3 --/
+        let a = LoroDoc::new_auto_commit();
+        a.set_peer_id(1).unwrap();
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        let c = LoroDoc::new_auto_commit();
+        c.set_peer_id(3).unwrap();
+        a.get_text("text").insert_(0, "a").unwrap();
+        b.import(&a.export_from(&Default::default())).unwrap();
+        c.import(&a.export_from(&Default::default())).unwrap();
+        b.get_text("text").insert_(0, "bb").unwrap();
+        c.get_text("text").insert_(0, "c").unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        a.import(&c.export_from(&a.oplog_vv())).unwrap();
+        a.get_text("text").insert_(0, "a").unwrap();
+
+        a.commit_then_renew();
+
+        let oplog = a.oplog().lock().unwrap();
+        assert_eq!(oplog.concurrent_changes(ID::new(1, 0)), vec![]);
+        assert_eq!(
+            oplog.concurrent_changes(ID::new(2, 1)),
+            vec![IdSpan::new(3, 0, 1)]
+        );
+        assert_eq!(
+            oplog.concurrent_changes(ID::new(3, 0)),
+            vec![IdSpan::new(2, 0, 2)]
+        );
+        assert_eq!(oplog.concurrent_changes(ID::new(1, 1)), vec![]);
+        assert_eq!(oplog.concurrent_changes(ID::new(4, 0)), vec![]);
+    }
+
+    #[test]
+    fn concurrent_changes_in_merged_change() {
+        // 2:0 --> 2:1 (merged into one change with 2:0)
+        //    \--> 3:0
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        let c = LoroDoc::new_auto_commit();
+        c.set_peer_id(3).unwrap();
+        b.get_text("text").insert_(0, "a").unwrap();
+        b.commit_then_renew();
+        c.import(&b.export_from(&Default::default())).unwrap();
+        b.get_text("text").insert_(0, "b").unwrap();
+        b.commit_then_renew();
+        c.get_text("text").insert_(0, "c").unwrap();
+        c.commit_then_renew();
+        assert_eq!(b.oplog().lock().unwrap().changes()[&2].len(), 1);
+        b.import(&c.export_from(&b.oplog_vv())).unwrap();
+
+        let oplog = b.oplog().lock().unwrap();
+        assert_eq!(
+            oplog.concurrent_changes(ID::new(3, 0)),
+            vec![IdSpan::new(2, 1, 2)]
+        );
+        assert_eq!(
+            oplog.concurrent_changes(ID::new(2, 1)),
+            vec![IdSpan::new(3, 0, 1)]
+        );
+        assert_eq!(oplog.concurrent_changes(ID::new(2, 0)), vec![]);
+    }
+
+    #[test]
+    fn ops_for_container() {
+        let doc = LoroDoc::new_auto_commit();
+        doc.set_peer_id(1).unwrap();
+        let text = doc.get_text("text");
+        text.insert_(0, "hello").unwrap();
+        doc.commit_then_renew();
+        text.delete_(1, 2).unwrap();
+        doc.commit_then_renew();
+        text.insert_(1, "X").unwrap();
+        doc.get_map("map").insert_("k", 1.into()).unwrap();
+        doc.commit_then_renew();
+
+        let oplog = doc.oplog().lock().unwrap();
+        let ops: Vec<_> = oplog.ops_for_container(&text.id()).collect();
+        assert_eq!(ops.len(), 3);
+        assert_eq!(ops[0].0, ID::new(1, 0));
+        assert_eq!(ops[1].0, ID::new(1, 5));
+        assert_eq!(ops[2].0, ID::new(1, 7));
+        let list_ops: Vec<_> = ops
+            .iter()
+            .map(|(_, content)| content.as_list().unwrap())
+            .collect();
+        assert!(matches!(
+            list_ops[0],
+            InnerListOp::InsertText {
+                pos: 0,
+                unicode_len: 5,
+                ..
+            }
+        ));
+        assert_eq!(list_ops[1].as_delete().unwrap().start(), 1);
+        assert_eq!(list_ops[1].as_delete().unwrap().len(), 2);
+        assert!(matches!(
+            list_ops[2],
+            InnerListOp::InsertText {
+                pos: 1,
+                unicode_len: 1,
+                ..
+            }
+        ));
+
+        let unknown = ContainerID::new_root("unknown", ContainerType::Text);
+        assert_eq!(oplog.ops_for_container(&unknown).count(), 0);
+    }
+
+    #[test]
+    fn container_op_count() {
+        let doc = LoroDoc::new_auto_commit();
+        let text = doc.get_text("text");
+        let map = doc.get_map("map");
+        text.insert_(0, "hello").unwrap();
+        doc.commit_then_renew();
+        text.delete_(0, 2).unwrap();
+        map.insert_("k", 1.into()).unwrap();
+        doc.commit_then_renew();
+        map.insert_("k", 2.into()).unwrap();
+        doc.commit_then_renew();
+
+        let oplog = doc.oplog().lock().unwrap();
+        assert_eq!(oplog.container_op_count(&text.id()), 7);
+        assert_eq!(oplog.container_op_count(&map.id()), 2);
+        // the local changes are merged
+        assert_eq!(oplog.container_change_count(&text.id()), 1);
+        assert_eq!(oplog.container_change_count(&map.id()), 1);
+        let unknown = ContainerID::new_root("unknown", ContainerType::List);
+        assert_eq!(oplog.container_op_count(&unknown), 0);
+        assert_eq!(oplog.container_change_count(&unknown), 0);
+    }
+
+    #[test]
+    fn op_observer() {
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let a = LoroDoc::new_auto_commit();
+        a.set_peer_id(1).unwrap();
+        let observed_clone = observed.clone();
+        a.oplog()
+            .lock()
+            .unwrap()
+            .set_op_observer(move |op, container| {
+                observed_clone.lock().unwrap().push((
+                    ID::new(op.peer, op.op.counter),
+                    op.end - op.start,
+                    container.clone(),
+                ));
+            });
+        let text = a.get_text("text");
+        let map = a.get_map("map");
+        text.insert_(0, "hello").unwrap();
+        text.delete_(0, 2).unwrap();
+        map.insert_("k", 1.into()).unwrap();
+        a.commit_then_renew();
+
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        b.get_text("text").insert_(0, "ab").unwrap();
+        a.import(&b.export_from(&Default::default())).unwrap();
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            vec![
+                (ID::new(1, 0), 5, text.id()),
+                (ID::new(1, 5), 2, text.id()),
+                (ID::new(1, 7), 1, map.id()),
+                (ID::new(2, 0), 2, text.id()),
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_changes_causal() {
+        let a = LoroDoc::new_auto_commit();
+        a.set_peer_id(1).unwrap();
+        let b = LoroDoc::new_auto_commit();
+        b.set_peer_id(2).unwrap();
+        let c = LoroDoc::new_auto_commit();
+        c.set_peer_id(3).unwrap();
+        a.get_text("text").insert_(0, "a").unwrap();
+        b.get_text("text").insert_(0, "b").unwrap();
+        c.import(&a.export_from(&c.oplog_vv())).unwrap();
+        c.get_text("text").insert_(1, "c").unwrap();
+        b.import(&c.export_from(&b.oplog_vv())).unwrap();
+        b.get_list("list").insert_(0, 1.into()).unwrap();
+        a.get_map("map").insert_("k", 1.into()).unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        b.import(&a.export_from(&b.oplog_vv())).unwrap();
+
+        let oplog = a.oplog().lock().unwrap();
+        let mut yielded = VersionVector::new();
+        let mut order = Vec::new();
+        for change in oplog.iter_changes_causal() {
+            for dep in change.deps.iter() {
+                assert!(yielded.includes_id(*dep));
+            }
+            yielded.extend_to_include_end_id(ID::new(change.id.peer, change.ctr_end()));
+            order.push(change.id);
+        }
+        assert_eq!(&yielded, oplog.vv());
+
+        // peers with the same history get the same order
+        let other: Vec<_> = b
+            .oplog()
+            .lock()
+            .unwrap()
+            .iter_changes_causal()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(order, other);
+
+        // reversed, every change is visited before its deps
+        let mut reversed: Vec<_> = oplog.iter_changes_causal().rev().map(|c| c.id).collect();
+        assert_eq!(reversed.len(), oplog.iter_changes_causal().len());
+        reversed.reverse();
+        assert_eq!(reversed, order);
+    }
+
+    #[test]
+    fn iter_peer_ops() {
+        let doc = LoroDoc::new_auto_commit();
+        doc.set_peer_id(1).unwrap();
+        doc.get_text("text").insert_(0, "ab").unwrap();
+        doc.get_map("map").insert_("k", 1.into()).unwrap();
+        doc.commit_then_renew();
+        doc.get_text("text").insert_(2, "c").unwrap();
+        doc.get_list("list").insert_(0, 1.into()).unwrap();
+        doc.commit_then_renew();
+
+        let oplog = doc.oplog().lock().unwrap();
+        let forward: Vec<_> = oplog.iter_peer_ops(1).map(|op| op.id_start()).collect();
+        assert_eq!(
+            forward,
+            vec![ID::new(1, 0), ID::new(1, 2), ID::new(1, 3), ID::new(1, 4)]
+        );
+        let mut backward: Vec<_> = oplog
+            .iter_peer_ops(1)
+            .rev()
+            .map(|op| op.id_start())
+            .collect();
+        backward.reverse();
+        assert_eq!(backward, forward);
+        // the last op of the peer on the text
+        let text = oplog.arena.id_to_idx(&doc.get_text("text").id()).unwrap();
+        assert_eq!(
+            oplog
+                .iter_peer_ops(1)
+                .rev()
+                .find(|op| op.op().container == text)
+                .map(|op| op.id_start()),
+            Some(ID::new(1, 3))
+        );
+        assert_eq!(
+            oplog.iter_peer_ops(2).next_back().map(|op| op.id_start()),
+            None
+        );
+    }
+
+    #[test]
+    fn import_with_report() {
+        let doc = LoroDoc::new_auto_commit();
+        doc.set_peer_id(1).unwrap();
+        doc.get_text("text").insert_(0, "ab").unwrap();
+        doc.commit_then_renew();
+        let vv = doc.oplog_vv();
+        doc.get_text("text").insert_(2, "c").unwrap();
+
+        let mut oplog = OpLog::new();
+        let report = oplog.import_with_report(&doc.export_from(&vv)).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                pending: vec![IdSpan::new(1, 2, 3)],
+                ..Default::default()
+            }
+        );
+        assert_eq!(oplog.pending_changes().count(), 1);
+
+        let all = doc.export_from(&Default::default());
+        let report = oplog.import_with_report(&all).unwrap();
+        let len = |spans: &[IdSpan]| spans.iter().map(|s| s.atom_len()).sum::<usize>();
+        assert_eq!(len(&report.new), 3);
+        assert!(report.already_present.is_empty() && report.pending.is_empty());
+        assert_eq!(oplog.vv(), &doc.oplog_vv());
+
+        let report = oplog.import_with_report(&all).unwrap();
+        assert_eq!(len(&report.already_present), 3);
+        assert!(report.new.is_empty() && report.pending.is_empty());
+    }
+
+    #[test]
+    fn export_and_import_pending() {
+        let doc = LoroDoc::new_auto_commit();
+        doc.set_peer_id(1).unwrap();
+        doc.get_text("text").insert_(0, "ab").unwrap();
+        doc.commit_then_renew();
+        let dep = doc.export_from(&Default::default());
+        let vv = doc.oplog_vv();
+        doc.get_text("text").insert_(2, "c").unwrap();
+        doc.commit_then_renew();
+
+        let mut oplog = OpLog::new();
+        oplog.decode(&doc.export_from(&vv)).unwrap();
+        assert_eq!(oplog.pending_changes().count(), 1);
+        let pending = oplog.export_pending();
+
+        // restart
+        let mut oplog = OpLog::new();
+        oplog.import_pending(&pending).unwrap();
+        assert_eq!(oplog.pending_changes().count(), 1);
+        assert!(oplog.vv().is_empty());
+        oplog.decode(&dep).unwrap();
+        assert_eq!(oplog.pending_changes().count(), 0);
+        assert_eq!(oplog.vv(), &doc.oplog_vv());
+
+        let new_doc = LoroDoc::new();
+        new_doc
+            .import(&oplog.export_from(&Default::default()))
+            .unwrap();
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    }
+
+    #[test]
+    fn validate_import() {
+        let doc = LoroDoc::new();
+        doc.set_peer_id(1).unwrap();
+        doc.set_configure(Configure {
+            merge_local_ops: false,
+            ..Default::default()
+        });
+        let text = doc.get_text("text");
+        for _ in 0..3 {
+            let mut txn = doc.txn().unwrap();
+            text.insert(&mut txn, 0, "ab").unwrap();
+            txn.commit().unwrap();
+        }
+        let big = doc.get_text("big");
+        let mut txn = doc.txn().unwrap();
+        big.insert(&mut txn, 0, &"a".repeat(100)).unwrap();
+        txn.commit().unwrap();
+        let src = doc.oplog().lock().unwrap();
+
+        let oplog = OpLog::new();
+        let all = src.export_changes_between(&Default::default(), src.vv());
+        assert_eq!(all[&1].len(), 4);
+        oplog.validate_import(&encode_changes(all.clone())).unwrap();
+        // the columnar encoding
+        oplog
+            .validate_import(&src.export_from(&Default::default()))
+            .unwrap();
+
+        // a gap in the counters
+        let mut gap = all.clone();
+        gap.get_mut(&1).unwrap().remove(1);
+        assert!(matches!(
+            oplog.validate_import(&encode_changes(gap)),
+            Err(LoroError::DecodeError(_))
+        ));
+
+        // the deps are missing
+        let mut orphan = all;
+        orphan.get_mut(&1).unwrap().remove(0);
+        assert!(oplog.validate_import(&encode_changes(orphan)).is_err());
+        let vv = src.dag.frontiers_to_vv(&src.changes[&1][1].deps).unwrap();
+        assert!(oplog.validate_import(&src.export_from(&vv)).is_err());
+        assert!(oplog.vv().is_empty());
+    }
+}
@@ -1,13 +1,17 @@
 use std::{collections::BTreeMap, ops::Deref};
 
 use crate::{
-    arena::OpConverter, change::Change, encoding::RemoteClientChanges, op::RemoteOp, OpLog,
-    VersionVector,
+    arena::OpConverter,
+    change::Change,
+    encoding::{decode_change_list, encode_change_list, with_decoded_changes, RemoteClientChanges},
+    op::RemoteOp,
+    version::Frontiers,
+    OpLog, VersionVector,
 };
 use fxhash::FxHashMap;
 use itertools::Itertools;
 use loro_common::{
-    Counter, CounterSpan, HasCounterSpan, HasIdSpan, HasLamportSpan, LoroError, PeerID, ID,
+    Counter, CounterSpan, HasCounterSpan, HasIdSpan, HasLamportSpan, IdSpan, LoroError, PeerID, ID,
 };
 use rle::RleVec;
 use smallvec::SmallVec;
@@ -30,6 +34,17 @@ impl Deref for PendingChange {
     }
 }
 
+/// Where the changes of an import went, see [OpLog::import_with_report].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// The spans that were applied to the oplog by this import.
+    pub new: Vec<IdSpan>,
+    /// The spans that were already included in the oplog before this import.
+    pub already_present: Vec<IdSpan>,
+    /// The spans that were buffered as pending changes because some of their deps are missing.
+    pub pending: Vec<IdSpan>,
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct PendingChanges {
     changes: FxHashMap<PeerID, BTreeMap<Counter, SmallVec<[PendingChange; 1]>>>,
@@ -37,19 +52,14 @@ pub(crate) struct PendingChanges {
 
 impl OpLog {
     // calculate all `id_last`(s) whose change can be applied
-    pub(super) fn apply_appliable_changes_and_cache_pending(
+    pub(super) fn apply_appliable_changes_and_cache_pending<'a>(
         &mut self,
-        remote_changes: RemoteClientChanges,
+        remote_changes: impl Iterator<Item = Change<RemoteOp<'a>>>,
         converter: &mut OpConverter,
         mut latest_vv: VersionVector,
     ) -> Vec<ID> {
         let mut ans = Vec::new();
-        for change in remote_changes
-            .into_values()
-            .filter(|c| !c.is_empty())
-            .flat_map(|c| c.into_iter())
-            .sorted_unstable_by_key(|c| c.lamport)
-        {
+        for change in remote_changes.sorted_unstable_by_key(|c| c.lamport) {
             let local_change = to_local_op(change, converter);
             let local_change = PendingChange::Known(local_change);
             match remote_change_apply_state(&latest_vv, &local_change) {
@@ -96,6 +106,132 @@ impl OpLog {
     }
 }
 
+impl OpLog {
+    /// Import the encoded changes like [OpLog::decode] and report which of them
+    /// were new, which were already present and which became pending.
+    ///
+    /// A single change may be split across the buckets if it partially overlaps
+    /// with the existing history. Pending changes that get applied because of this
+    /// import are not part of the report.
+    pub fn import_with_report(&mut self, data: &[u8]) -> Result<ImportReport, LoroError> {
+        let old_vv = self.vv().clone();
+        self.incoming_spans = Some(Vec::new());
+        let ans = self.decode(data);
+        let incoming = self.incoming_spans.take().unwrap();
+        ans?;
+
+        let mut report = ImportReport::default();
+        for span in incoming {
+            let peer = span.client_id;
+            let CounterSpan { start, end } = span.counter;
+            let old_end = old_vv.get(&peer).copied().unwrap_or(0).clamp(start, end);
+            let new_end = self
+                .vv()
+                .get(&peer)
+                .copied()
+                .unwrap_or(0)
+                .clamp(old_end, end);
+            if start < old_end {
+                report
+                    .already_present
+                    .push(IdSpan::new(peer, start, old_end));
+            }
+            if old_end < new_end {
+                report.new.push(IdSpan::new(peer, old_end, new_end));
+            }
+            if new_end < end {
+                report.pending.push(IdSpan::new(peer, new_end, end));
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub(crate) fn record_incoming<'a, O: 'a>(
+        &mut self,
+        changes: impl Iterator<Item = &'a Change<O>>,
+    ) where
+        Change<O>: HasIdSpan,
+    {
+        if let Some(spans) = &mut self.incoming_spans {
+            spans.extend(changes.map(|c| c.id_span()));
+        }
+    }
+
+    /// Iterate over the pending changes and their deps.
+    ///
+    /// Pending changes are the imported changes that cannot be applied yet
+    /// because some of their deps are missing.
+    pub fn pending_changes(&self) -> impl Iterator<Item = (ID, Frontiers)> + '_ {
+        self.pending_changes
+            .changes
+            .values()
+            .flat_map(|tree| tree.values())
+            .flat_map(|changes| changes.iter())
+            .map(|change| (change.id, change.deps.clone()))
+    }
+
+    /// Encode the pending changes, so they can be restored by [OpLog::import_pending]
+    /// after a restart instead of being requested from the other peers again.
+    pub fn export_pending(&self) -> Vec<u8> {
+        encode_change_list(
+            self.pending_changes
+                .changes
+                .values()
+                .flat_map(|tree| tree.values())
+                .flat_map(|changes| changes.iter())
+                .map(|change| self.convert_change_to_remote(change)),
+        )
+    }
+
+    /// Restore the pending changes exported by [OpLog::export_pending].
+    ///
+    /// The changes whose deps are already included are applied right away, and only
+    /// to the oplog, like [OpLog::decode]. Use [crate::LoroDoc::import_pending] to
+    /// apply them to the state of a doc too.
+    ///
+    /// Return [LoroError::DecodeError] without importing anything if the data is invalid.
+    pub fn import_pending(&mut self, data: &[u8]) -> Result<(), LoroError> {
+        let changes = decode_change_list(data)?;
+        check_pending_changes(&changes)?;
+        let latest_vv = self.dag.vv.clone();
+        let ids = self.arena.clone().with_op_converter(|converter| {
+            self.apply_appliable_changes_and_cache_pending(
+                changes.into_iter(),
+                converter,
+                latest_vv,
+            )
+        });
+        let mut latest_vv = self.dag.vv.clone();
+        self.try_apply_pending(ids, &mut latest_vv);
+        self.notify_op_observer();
+        if !self.batch_importing {
+            self.dag.refresh_frontiers();
+        }
+        Ok(())
+    }
+
+    /// Drop the pending changes that overlap with the given span.
+    ///
+    /// It's useful when the missing deps will never arrive, e.g. the peer that
+    /// sent them has left. The applied changes are not affected. The pending
+    /// changes that depend on the dropped ones stay pending.
+    pub fn drop_pending(&mut self, mut span: IdSpan) {
+        span.normalize_();
+        self.pending_changes.changes.retain(|_, tree| {
+            tree.retain(|_, changes| {
+                changes.retain(|change| {
+                    change.id.peer != span.client_id
+                        || change.ctr_end() <= span.counter.start
+                        || change.id.counter >= span.counter.end
+                });
+                !changes.is_empty()
+            });
+            !tree.is_empty()
+        });
+    }
+}
+
 impl OpLog {
     pub(super) fn check_changes(&self, changes: &RemoteClientChanges) -> Result<(), LoroError> {
         for changes in changes.values() {
@@ -126,6 +262,101 @@ impl OpLog {
         Ok(())
     }
 
+    /// Check the changes in the bytes exported by [OpLog::export_from] without importing them.
+    ///
+    /// It returns the first violation of
+    ///
+    /// - the counters of each peer are continuous,
+    /// - the lamports of each peer are increasing, if they are encoded,
+    /// - every dep is either included in this oplog or in the data,
+    /// - no dep is at or after the change of the same peer.
+    ///
+    /// The encodings derive the counters of a peer from the first one, so a change
+    /// missing in the middle shifts the ids of the later ones and is caught by the
+    /// last rule.
+    ///
+    /// The changes that are already included are fine, the import skips them.
+    /// A relay can use it to reject bad data before forwarding it.
+    pub fn validate_import(&self, data: &[u8]) -> Result<(), LoroError> {
+        with_decoded_changes(data, |changes, lamport_known| {
+            self.validate_changes(changes, lamport_known)
+        })
+    }
+
+    fn validate_changes(
+        &self,
+        changes: &[Change<RemoteOp>],
+        lamport_known: bool,
+    ) -> Result<(), LoroError> {
+        let mut peer_changes: FxHashMap<PeerID, Vec<&Change<RemoteOp>>> = FxHashMap::default();
+        for change in changes {
+            if change.id.counter < 0 {
+                return Err(LoroError::DecodeError(
+                    "Invalid data. Negative id counter.".into(),
+                ));
+            }
+            peer_changes.entry(change.id.peer).or_default().push(change);
+        }
+
+        let mut batch_spans = FxHashMap::default();
+        for (peer, changes) in peer_changes.iter_mut() {
+            changes.sort_unstable_by_key(|c| c.id.counter);
+            for pair in changes.windows(2) {
+                let (last, change) = (pair[0], pair[1]);
+                if change.id.counter != last.ctr_end() {
+                    return Err(LoroError::DecodeError(
+                        format!("Invalid data. Not continuous counter at {:?}", change.id)
+                            .into_boxed_str(),
+                    ));
+                }
+                if lamport_known && change.lamport < last.lamport_end() {
+                    return Err(LoroError::DecodeError(
+                        format!("Invalid data. Decreasing lamport at {:?}", change.id)
+                            .into_boxed_str(),
+                    ));
+                }
+            }
+            let start = changes.first().unwrap().id.counter;
+            let end = changes.last().unwrap().ctr_end();
+            batch_spans.insert(*peer, CounterSpan::new(start, end));
+        }
+
+        let includes = |id: ID| {
+            self.vv().includes_id(id)
+                || batch_spans
+                    .get(&id.peer)
+                    .is_some_and(|span| span.contains(id.counter))
+        };
+        for change in changes {
+            if let Some(dep) = change
+                .deps
+                .iter()
+                .find(|dep| dep.peer == change.id.peer && dep.counter >= change.id.counter)
+            {
+                return Err(LoroError::DecodeError(
+                    format!("Invalid data. Dep {:?} is not before {:?}", dep, change.id)
+                        .into_boxed_str(),
+                ));
+            }
+
+            let prev = (change.id.counter > 0).then(|| change.id.inc(-1));
+            if let Some(dep) = change
+                .deps
+                .iter()
+                .copied()
+                .chain(prev)
+                .find(|&dep| !includes(dep))
+            {
+                return Err(LoroError::DecodeError(
+                    format!("Invalid data. Missing dep {:?} of {:?}", dep, change.id)
+                        .into_boxed_str(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn try_apply_pending(
         &mut self,
         mut id_stack: Vec<ID>,
@@ -193,8 +424,44 @@ impl OpLog {
         self.dag.vv.extend_to_include_last_id(change.id_last());
         self.latest_timestamp = self.latest_timestamp.max(change.timestamp);
         let mark = self.insert_dag_node_on_new_change(&change);
-        self.insert_new_change(change, mark);
+        self.insert_new_change(change, mark, false);
+    }
+}
+
+/// Check the rules of [OpLog::validate_import] that don't need the deps, because
+/// the deps of pending changes are missing by definition.
+fn check_pending_changes(changes: &[Change<RemoteOp>]) -> Result<(), LoroError> {
+    let mut changes: Vec<_> = changes.iter().collect();
+    changes.sort_unstable_by_key(|c| (c.id.peer, c.id.counter));
+    for change in changes.iter() {
+        if change.id.counter < 0 {
+            return Err(LoroError::DecodeError(
+                "Invalid data. Negative id counter.".into(),
+            ));
+        }
+
+        if let Some(dep) = change
+            .deps
+            .iter()
+            .find(|dep| dep.peer == change.id.peer && dep.counter >= change.id.counter)
+        {
+            return Err(LoroError::DecodeError(
+                format!("Invalid data. Dep {:?} is not before {:?}", dep, change.id)
+                    .into_boxed_str(),
+            ));
+        }
+    }
+
+    for pair in changes.windows(2) {
+        let (last, change) = (pair[0], pair[1]);
+        if last.id.peer == change.id.peer && change.id.counter < last.ctr_end() {
+            return Err(LoroError::DecodeError(
+                format!("Invalid data. Overlapping changes at {:?}", change.id).into_boxed_str(),
+            ));
+        }
     }
+
+    Ok(())
 }
 
 pub(super) fn to_local_op(change: Change<RemoteOp>, converter: &mut OpConverter) -> Change {
@@ -249,7 +516,12 @@ fn remote_change_apply_state(vv: &VersionVector, change: &Change) -> ChangeApply
 
 #[cfg(test)]
 mod test {
-    use crate::{LoroDoc, ToJson, VersionVector};
+    use loro_common::{IdSpan, LoroError, ID};
+
+    use crate::{
+        encoding::{decode_change_list, encode_change_list},
+        LoroDoc, ToJson, VersionVector,
+    };
 
     #[test]
     fn import_pending() {
@@ -281,6 +553,72 @@ mod test {
         assert_eq!(a.get_deep_value(), b.get_deep_value());
     }
 
+    #[test]
+    fn list_and_drop_pending() {
+        let a = LoroDoc::new();
+        a.set_peer_id(1).unwrap();
+        let b = LoroDoc::new();
+        b.set_peer_id(2).unwrap();
+        let text_a = a.get_text("text");
+        a.with_txn(|txn| text_a.insert(txn, 0, "a")).unwrap();
+        let version1 = a.oplog_vv();
+        a.with_txn(|txn| text_a.insert(txn, 0, "b")).unwrap();
+        let update2 = a.export_from(&version1);
+        b.import(&update2).unwrap();
+
+        let pending: Vec<_> = b.oplog().lock().unwrap().pending_changes().collect();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0, ID::new(1, 1));
+        assert_eq!(pending[0].1, ID::new(1, 0).into());
+
+        b.oplog()
+            .lock()
+            .unwrap()
+            .drop_pending(IdSpan::new(2, 0, 10));
+        assert_eq!(b.oplog().lock().unwrap().pending_changes().count(), 1);
+        b.oplog().lock().unwrap().drop_pending(IdSpan::new(1, 0, 2));
+        assert_eq!(b.oplog().lock().unwrap().pending_changes().count(), 0);
+
+        // the dropped change can still be imported again later
+        b.import(&a.export_from(&Default::default())).unwrap();
+        assert_eq!(a.get_deep_value(), b.get_deep_value());
+    }
+
+    #[test]
+    fn import_pending_to_doc() {
+        let a = LoroDoc::new();
+        a.set_peer_id(1).unwrap();
+        let text_a = a.get_text("text");
+        a.with_txn(|txn| text_a.insert(txn, 0, "a")).unwrap();
+        let update1 = a.export_from(&VersionVector::default());
+        let version1 = a.oplog_vv();
+        a.with_txn(|txn| text_a.insert(txn, 1, "b")).unwrap();
+        let b = LoroDoc::new();
+        b.import(&a.export_from(&version1)).unwrap();
+        let pending = b.export_pending();
+
+        // the deps arrived before the pending changes are restored
+        let c = LoroDoc::new();
+        c.import(&update1).unwrap();
+        c.import_pending(&pending).unwrap();
+        assert_eq!(c.oplog().lock().unwrap().pending_changes().count(), 0);
+        assert_eq!(a.get_deep_value(), c.get_deep_value());
+
+        // invalid data is rejected without importing anything
+        let d = LoroDoc::new();
+        assert!(matches!(
+            d.import_pending(&[1, 2, 3]),
+            Err(LoroError::DecodeError(_))
+        ));
+        let changes = decode_change_list(&pending).unwrap();
+        let overlapping = encode_change_list(changes.iter().chain(changes.iter()).cloned());
+        assert!(matches!(
+            d.import_pending(&overlapping),
+            Err(LoroError::DecodeError(_))
+        ));
+        assert_eq!(d.oplog().lock().unwrap().pending_changes().count(), 0);
+    }
+
     #[test]
     fn pending_import_snapshot() {
         let a = LoroDoc::new();
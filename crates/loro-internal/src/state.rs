@@ -3,18 +3,22 @@ use std::{borrow::Cow, sync::Arc};
 use enum_as_inner::EnumAsInner;
 use enum_dispatch::enum_dispatch;
 use fxhash::{FxHashMap, FxHashSet};
-use loro_common::{ContainerID, LoroResult};
+use loro_common::{ContainerID, HasIdSpan, LoroResult};
+use rle::{HasLength, Sliceable};
 
 use crate::{
+    change::Lamport,
     configure::{DefaultRandom, SecureRandomGenerator},
-    container::{idx::ContainerIdx, ContainerIdRaw},
+    container::{idx::ContainerIdx, list::list_op::InnerListOp, ContainerIdRaw},
     event::{Diff, Index},
     event::{InternalContainerDiff, InternalDiff},
     fx_map,
-    id::PeerID,
-    op::{Op, RawOp},
+    id::{PeerID, ID},
+    op::{InnerContent, Op, RawOp},
+    oplog::OpLog,
+    utils::lock::Recover,
     version::Frontiers,
-    ContainerType, InternalString, LoroValue,
+    ContainerType, InternalString, LoroValue, VersionVector,
 };
 
 mod list_state;
@@ -29,7 +33,7 @@ pub(crate) use tree_state::{get_meta_value, TreeState};
 
 use super::{
     arena::SharedArena,
-    event::{ContainerDiff, DocDiff, InternalDocDiff},
+    event::{ContainerDiff, DocDiff, DocSignal, InternalDocDiff},
 };
 
 #[derive(Clone)]
@@ -110,6 +114,16 @@ impl State {
     }
 }
 
+impl Recover for DocState {
+    /// Roll back the txn that was in progress, so that the state matches its
+    /// frontiers again and a new txn can be started.
+    fn recover(&mut self) {
+        if self.in_txn {
+            self.abort_txn();
+        }
+    }
+}
+
 impl DocState {
     #[inline]
     pub fn new(arena: SharedArena) -> Self {
@@ -135,6 +149,18 @@ impl DocState {
         self.event_recorder.diff_start_version = Some(self.frontiers.clone());
     }
 
+    /// Record which containers are changed, without recording the diffs.
+    ///
+    /// It's used when all the subscribers are in [crate::obs::NotifyMode::Signal].
+    pub fn start_recording_signal(&mut self) {
+        self.event_recorder.recording_signal = true;
+    }
+
+    #[inline(always)]
+    fn is_recording_signal(&self) -> bool {
+        self.event_recorder.recording_signal && !self.event_recorder.recording_diff
+    }
+
     #[inline(always)]
     pub fn stop_and_clear_recording(&mut self) {
         self.event_recorder = Default::default();
@@ -159,6 +185,22 @@ impl DocState {
         std::mem::take(&mut self.event_recorder.events)
     }
 
+    /// Take all the recorded signals. They are older than the events returned by [Self::take_events].
+    pub(crate) fn take_signals(&mut self) -> Vec<DocSignal> {
+        std::mem::take(&mut self.event_recorder.signals)
+    }
+
+    fn record_signal(&mut self, containers: Vec<ContainerIdx>) {
+        if containers.is_empty() {
+            return;
+        }
+
+        self.event_recorder.signals.push(DocSignal {
+            to: self.frontiers.clone(),
+            containers,
+        });
+    }
+
     /// Record the next diff.
     /// Caller should call [pre_txn] before calling this.
     ///
@@ -327,11 +369,91 @@ impl DocState {
             }
         }
 
+        let signal = self
+            .is_recording_signal()
+            .then(|| diffs.iter().map(|x| x.idx).collect());
         diff.diff = diffs.into();
         self.frontiers = (*diff.new_version).to_owned();
         if self.is_recording() {
             self.record_diff(diff)
         }
+
+        if let Some(containers) = signal {
+            self.record_signal(containers);
+        }
+    }
+
+    /// Apply the changes of `oplog` after `from` to the state directly, without the diff calculation.
+    ///
+    /// It only works when the changes are a linear history on top of the current state,
+    /// e.g. when the doc is only edited by a single peer. Then every op was created on
+    /// exactly the state before it, so it can be applied like a local op.
+    ///
+    /// Return false without touching the state if it's not the case, the diffs are recorded,
+    /// or an op can't be applied. The ops are applied in a txn of the states, so that a
+    /// failing op rolls back the ones before it and the caller can take the tracker path.
+    pub(crate) fn apply_linear_changes(&mut self, oplog: &OpLog, from: &VersionVector) -> bool {
+        if self.in_txn || self.is_recording() {
+            return false;
+        }
+
+        let mut changes = Vec::new();
+        let mut frontiers = self.frontiers.clone();
+        for (change, _) in oplog.iter_causally(from.clone(), oplog.vv().clone()) {
+            let start = from.get(&change.id.peer).copied().unwrap_or(0);
+            let change = if change.id.counter < start {
+                Cow::Owned(change.slice((start - change.id.counter) as usize, change.atom_len()))
+            } else {
+                Cow::Borrowed(change)
+            };
+            if change.deps != frontiers {
+                return false;
+            }
+
+            frontiers = Frontiers::from_id(change.id_last());
+            changes.push(change);
+        }
+
+        self.in_txn = true;
+        for change in changes.iter() {
+            for op in change.ops.iter() {
+                let mut remote_ops = oplog.local_op_to_remote(op);
+                debug_assert_eq!(remote_ops.len(), 1);
+                let raw_op = RawOp {
+                    id: ID::new(change.id.peer, op.counter),
+                    lamport: change.lamport + (op.counter - change.id.counter) as Lamport,
+                    container: op.container,
+                    content: remote_ops.pop().unwrap().content,
+                };
+                // the text inserted by remote ops refers to the unicode range in the arena,
+                // while the text state only accepts the local form
+                let op = match &op.content {
+                    InnerContent::List(InnerListOp::Insert { slice, pos })
+                        if op.container.get_type() == ContainerType::Text =>
+                    {
+                        let range = slice.0.start as usize..slice.0.end as usize;
+                        Cow::Owned(Op::new(
+                            raw_op.id,
+                            InnerContent::List(InnerListOp::InsertText {
+                                slice: self.arena.slice_by_unicode(range),
+                                unicode_start: slice.0.start,
+                                unicode_len: slice.0.end - slice.0.start,
+                                pos: *pos as u32,
+                            }),
+                            op.container,
+                        ))
+                    }
+                    _ => Cow::Borrowed(op),
+                };
+                if self.apply_local_op(&raw_op, &op).is_err() {
+                    self.abort_txn();
+                    return false;
+                }
+            }
+        }
+
+        self.commit_txn(frontiers, None);
+        true
     }
 
     pub fn apply_local_op(&mut self, raw_op: &RawOp, op: &Op) -> LoroResult<()> {
@@ -364,8 +486,9 @@ impl DocState {
     }
 
     pub(crate) fn commit_txn(&mut self, new_frontiers: Frontiers, diff: Option<InternalDocDiff>) {
-        for container_idx in std::mem::take(&mut self.changed_idx_in_txn) {
-            self.states.get_mut(&container_idx).unwrap().commit_txn();
+        let changed = std::mem::take(&mut self.changed_idx_in_txn);
+        for container_idx in changed.iter() {
+            self.states.get_mut(container_idx).unwrap().commit_txn();
         }
 
         self.in_txn = false;
@@ -373,6 +496,10 @@ impl DocState {
         if self.is_recording() {
             self.record_diff(diff.unwrap());
         }
+
+        if self.is_recording_signal() {
+            self.record_signal(changed.into_iter().collect());
+        }
     }
 
     #[inline]
@@ -436,6 +563,9 @@ impl DocState {
         }
 
         self.frontiers = frontiers;
+        if self.is_recording_signal() {
+            self.record_signal(self.states.keys().copied().collect());
+        }
     }
 
     /// id can be a str, ContainerID, or ContainerIdRaw.
@@ -494,10 +624,54 @@ impl DocState {
         self.in_txn
     }
 
+    /// Whether the txn in progress has changed the state, i.e. the state
+    /// contains changes that are not in the oplog yet.
+    pub(crate) fn has_uncommitted_changes(&self) -> bool {
+        !self.changed_idx_in_txn.is_empty()
+    }
+
+    /// Copy the state, so that diffs can be applied to the copy without changing this state.
+    ///
+    /// Return None if the txn in progress has changed the state, because the
+    /// uncommitted changes can't be removed from the copy.
+    pub(crate) fn fork(&self) -> Option<DocState> {
+        if self.has_uncommitted_changes() {
+            return None;
+        }
+
+        let mut state = self.clone();
+        state.in_txn = false;
+        state.event_recorder = Default::default();
+        Some(state)
+    }
+
     pub fn is_empty(&self) -> bool {
         !self.in_txn && self.states.is_empty() && self.arena.can_import_snapshot()
     }
 
+    /// Get the index of `child` in its parent container.
+    ///
+    /// Return None if it's a root container or it has been detached from its parent.
+    pub(crate) fn get_index_in_parent(&self, child: ContainerIdx) -> Option<Index> {
+        let parent = self.arena.get_parent(child)?;
+        let id = self.arena.idx_to_id(child)?;
+        self.states.get(&parent)?.get_child_index(&id)
+    }
+
+    /// Whether the container is detached from the root containers, e.g. it or one of
+    /// its ancestors has been deleted from its parent.
+    pub(crate) fn is_deleted(&self, mut idx: ContainerIdx) -> bool {
+        loop {
+            let Some(parent) = self.arena.get_parent(idx) else {
+                return !self.arena.idx_to_id(idx).unwrap().is_root();
+            };
+            if self.get_index_in_parent(idx).is_none() {
+                return true;
+            }
+            idx = parent;
+        }
+    }
+
     pub fn get_deep_value(&mut self) -> LoroValue {
         let roots = self.arena.root_containers();
         let mut ans = FxHashMap::with_capacity_and_hasher(roots.len(), Default::default());
@@ -847,6 +1021,9 @@ pub fn create_state(idx: ContainerIdx) -> State {
 #[derive(Default, Clone)]
 struct EventRecorder {
     recording_diff: bool,
+    /// Only record the changed containers, it's ignored when `recording_diff` is true
+    recording_signal: bool,
+    signals: Vec<DocSignal>,
     // A batch of diffs will be converted to a event when
     // they cannot be merged with the next diff.
     diffs: Vec<InternalDocDiff<'static>>,
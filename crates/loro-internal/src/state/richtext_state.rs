@@ -2,7 +2,7 @@ use std::{ops::Range, sync::Arc};
 
 use fxhash::FxHashMap;
 use generic_btree::rle::{HasLength, Mergeable};
-use loro_common::{Counter, LoroResult, LoroValue, PeerID, ID};
+use loro_common::{Counter, LoroError, LoroResult, LoroValue, PeerID, ID};
 use loro_preload::{CommonArena, EncodedRichtextState, TempArena, TextRanges};
 
 use crate::{
@@ -30,6 +30,10 @@ pub struct RichtextState {
     pub(crate) state: Box<LazyLoad<RichtextStateLoader, InnerState>>,
     in_txn: bool,
     undo_stack: Vec<UndoItem>,
+    /// The cached value of [ContainerState::get_value], so reading the text repeatedly
+    /// between edits doesn't rebuild the string. Every method that changes the text
+    /// must reset it.
+    value_cache: Option<Arc<String>>,
 }
 
 impl RichtextState {
@@ -40,6 +44,7 @@ impl RichtextState {
             state: Box::new(LazyLoad::new_dst(Default::default())),
             in_txn: false,
             undo_stack: Default::default(),
+            value_cache: None,
         }
     }
 
@@ -48,6 +53,12 @@ impl RichtextState {
         self.state.get_mut().to_string()
     }
 
+    /// Like [Self::as_string], but the text is borrowed if it's stored in a single chunk.
+    #[inline]
+    pub fn as_str_cow(&mut self) -> std::borrow::Cow<'_, str> {
+        self.state.get_mut().to_str_cow()
+    }
+
     #[inline(always)]
     pub(crate) fn is_empty(&self) -> bool {
         match &*self.state {
@@ -71,6 +82,7 @@ impl Clone for RichtextState {
             state: self.state.clone(),
             in_txn: false,
             undo_stack: Vec::new(),
+            value_cache: self.value_cache.clone(),
         }
     }
 }
@@ -133,6 +145,7 @@ impl Mergeable for UndoItem {
 impl ContainerState for RichtextState {
     // TODO: refactor
     fn apply_diff_and_convert(&mut self, diff: InternalDiff, _arena: &SharedArena) -> Diff {
+        self.value_cache = None;
         let InternalDiff::RichtextRaw(richtext) = diff else {
             unreachable!()
         };
@@ -257,6 +270,7 @@ impl ContainerState for RichtextState {
     }
 
     fn apply_diff(&mut self, diff: InternalDiff, _arena: &SharedArena) {
+        self.value_cache = None;
         let InternalDiff::RichtextRaw(richtext) = diff else {
             unreachable!()
         };
@@ -319,6 +333,7 @@ impl ContainerState for RichtextState {
     }
 
     fn apply_op(&mut self, r_op: &RawOp, op: &Op, _arena: &SharedArena) -> LoroResult<()> {
+        self.value_cache = None;
         match &op.content {
             crate::op::InnerContent::List(l) => match l {
                 list_op::InnerListOp::Insert { slice: _, pos: _ } => {
@@ -417,12 +432,16 @@ impl ContainerState for RichtextState {
 
     // value is a list
     fn get_value(&mut self) -> LoroValue {
-        LoroValue::String(Arc::new(self.state.get_mut().to_string()))
+        let value = self
+            .value_cache
+            .get_or_insert_with(|| Arc::new(self.state.get_mut().to_string()));
+        LoroValue::String(value.clone())
     }
 }
 
 impl RichtextState {
     fn undo_all(&mut self) {
+        self.value_cache = None;
         while let Some(item) = self.undo_stack.pop() {
             match item {
                 UndoItem::Insert { index, len } => {
@@ -482,6 +501,16 @@ impl RichtextState {
         self.state.get_mut().len_unicode()
     }
 
+    /// The length in Event Index, i.e. UTF-16 if feature="wasm", Unicode otherwise
+    #[inline(always)]
+    pub(crate) fn len_event(&mut self) -> usize {
+        if cfg!(feature = "wasm") {
+            self.len_utf16()
+        } else {
+            self.len_unicode()
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn get_entity_index_for_text_insert(&mut self, event_index: usize) -> usize {
         self.state
@@ -507,6 +536,18 @@ impl RichtextState {
             .get_text_entity_ranges(pos, len, PosType::Event)
     }
 
+    #[inline(always)]
+    pub(crate) fn entity_index_to_event_index(&mut self, entity_index: usize) -> usize {
+        self.state
+            .get_mut()
+            .entity_index_to_event_index(entity_index)
+    }
+
+    #[inline(always)]
+    pub(crate) fn char_at(&mut self, unicode_index: usize) -> Option<char> {
+        self.state.get_mut().char_at(unicode_index)
+    }
+
     #[inline(always)]
     pub fn get_richtext_value(&mut self) -> LoroValue {
         self.state.get_mut().get_richtext_value()
@@ -530,6 +571,292 @@ impl RichtextState {
         }
     }
 
+    /// Find the end of the next word at or after the unicode index `pos`.
+    ///
+    /// Non-word chars after `pos` are skipped first, then the following word.
+    /// A word is a run of alphanumeric chars or `_`. The chunks are scanned in
+    /// place, so a word may span several of them.
+    ///
+    /// Returns the unicode index of the boundary and the entity index of the
+    /// last char before it, if there is one.
+    pub(crate) fn next_word_boundary(&self, pos: usize) -> (usize, Option<usize>) {
+        let mut unicode_index = 0;
+        let mut entity_index = 0;
+        let mut last_char_entity_index = None;
+        let mut in_word = false;
+        for chunk in self.iter_chunk() {
+            let Some(text) = chunk.as_str() else {
+                entity_index += 1;
+                continue;
+            };
+
+            for c in text.chars() {
+                if unicode_index >= pos {
+                    let is_word_char = c.is_alphanumeric() || c == '_';
+                    if in_word && !is_word_char {
+                        return (unicode_index, last_char_entity_index);
+                    }
+
+                    in_word |= is_word_char;
+                }
+
+                last_char_entity_index = Some(entity_index);
+                unicode_index += 1;
+                entity_index += 1;
+            }
+        }
+
+        (unicode_index, last_char_entity_index)
+    }
+
+    /// Get the text in the unicode range `start..end` by walking the chunks in place,
+    /// so only the overlapping chunks are copied.
+    pub(crate) fn slice(&self, start: usize, end: usize) -> String {
+        let mut ans = String::new();
+        let mut unicode_index = 0;
+        for chunk in self.iter_chunk() {
+            if unicode_index >= end {
+                break;
+            }
+
+            let Some(text) = chunk.as_str() else {
+                continue;
+            };
+
+            let len = text.chars().count();
+            if unicode_index + len > start {
+                ans.extend(
+                    text.chars()
+                        .skip(start.saturating_sub(unicode_index))
+                        .take(end.min(unicode_index + len) - start.max(unicode_index)),
+                );
+            }
+
+            unicode_index += len;
+        }
+
+        ans
+    }
+
+    /// Find the non-overlapping occurrences of `needle` that start at or after the Event
+    /// Index `from`, and call `f` with the Event Index of each of them until it returns false.
+    ///
+    /// The chunks are scanned in place. A partial match at the end of a chunk is carried
+    /// over to the next one, so a match may span several chunks. An empty `needle` matches
+    /// at `from` only. Nothing matches if `from` is out of bound or inside a char.
+    pub(crate) fn find(&self, needle: &str, from: usize, mut f: impl FnMut(usize) -> bool) {
+        let char_len = |c: char| {
+            if cfg!(feature = "wasm") {
+                c.len_utf16()
+            } else {
+                1
+            }
+        };
+        let needle: Vec<char> = needle.chars().collect();
+        let needle_len: usize = needle.iter().map(|&c| char_len(c)).sum();
+        // `prefix[i]` is the length of the longest proper prefix of `needle[..=i]`
+        // that is also its suffix, so a failed match can resume from there
+        let mut prefix = vec![0; needle.len()];
+        let mut len = 0;
+        for i in 1..needle.len() {
+            while len > 0 && needle[i] != needle[len] {
+                len = prefix[len - 1];
+            }
+
+            if needle[i] == needle[len] {
+                len += 1;
+            }
+
+            prefix[i] = len;
+        }
+
+        let mut event_index = 0;
+        let mut started = false;
+        let mut matched = 0;
+        for chunk in self.iter_chunk() {
+            let Some(text) = chunk.as_str() else {
+                continue;
+            };
+
+            for c in text.chars() {
+                if event_index < from {
+                    event_index += char_len(c);
+                    continue;
+                }
+
+                if !started {
+                    if event_index != from {
+                        return;
+                    }
+
+                    if needle.is_empty() {
+                        f(from);
+                        return;
+                    }
+
+                    started = true;
+                }
+
+                while matched > 0 && c != needle[matched] {
+                    matched = prefix[matched - 1];
+                }
+
+                if c == needle[matched] {
+                    matched += 1;
+                }
+
+                event_index += char_len(c);
+                if matched == needle.len() {
+                    if !f(event_index - needle_len) {
+                        return;
+                    }
+
+                    matched = 0;
+                }
+            }
+        }
+
+        if !started && event_index == from && needle.is_empty() {
+            f(from);
+        }
+    }
+
+    /// Convert the 0-based `line` and Unicode `col` to an Event Index, see
+    /// [crate::handler::TextHandler::line_col_to_pos].
+    ///
+    /// The chunks are scanned in place, and the scan stops once the position is found.
+    pub(crate) fn line_col_to_event_index(&self, line: usize, col: usize) -> LoroResult<usize> {
+        let mut event_index = 0;
+        let mut line_index = 0;
+        // the number of chars of the target line that have been scanned
+        let mut line_len = 0;
+        let mut last_char = None;
+        let mut ended_by_newline = false;
+        'scan: for chunk in self.iter_chunk() {
+            let Some(text) = chunk.as_str() else {
+                continue;
+            };
+
+            for c in text.chars() {
+                if line_index < line {
+                    if c == '\n' {
+                        line_index += 1;
+                    }
+                } else if c == '\n' {
+                    ended_by_newline = true;
+                    break 'scan;
+                } else if line_len == col {
+                    // `c` follows the position and it's not a line break,
+                    // so the position is inside the content of the line
+                    return Ok(event_index);
+                } else {
+                    line_len += 1;
+                    last_char = Some(c);
+                }
+
+                event_index += if cfg!(feature = "wasm") {
+                    c.len_utf16()
+                } else {
+                    1
+                };
+            }
+        }
+
+        if line_index < line {
+            return Err(LoroError::OutOfBound {
+                pos: line,
+                len: line_index + 1,
+            });
+        }
+
+        // a `\r` before the line break is not part of the line
+        let len = if ended_by_newline && last_char == Some('\r') {
+            line_len - 1
+        } else {
+            line_len
+        };
+        if col > len {
+            return Err(LoroError::OutOfBound { pos: col, len });
+        }
+
+        Ok(event_index)
+    }
+
+    /// Convert a UTF-16 range to a unicode range, resolving both ends in a single
+    /// walk over the chunks.
+    ///
+    /// Return None if `utf16_range.end` is out of bound.
+    ///
+    /// # Panic
+    ///
+    /// Panic if either end of the range is in the middle of a surrogate pair.
+    pub(crate) fn utf16_range_to_unicode_range(
+        &self,
+        utf16_range: Range<usize>,
+    ) -> Option<Range<usize>> {
+        match self.checked_utf16_range_to_unicode_range(utf16_range) {
+            Ok(range) => Some(range),
+            Err(LoroError::OutOfBound { .. }) => None,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like [Self::utf16_range_to_unicode_range], but never panics.
+    ///
+    /// Return [LoroError::OutOfBound] if `utf16_range.end` is out of bound, or
+    /// [LoroError::ArgErr] if either end is in the middle of a surrogate pair.
+    pub(crate) fn checked_utf16_range_to_unicode_range(
+        &self,
+        utf16_range: Range<usize>,
+    ) -> LoroResult<Range<usize>> {
+        debug_assert!(utf16_range.start <= utf16_range.end);
+        let mut start = None;
+        let mut unicode_index = 0;
+        let mut current_utf16_index = 0;
+        for chunk in self.iter_chunk() {
+            let Some(text) = chunk.as_str() else {
+                continue;
+            };
+
+            for c in text.chars() {
+                if current_utf16_index == utf16_range.start {
+                    start.get_or_insert(unicode_index);
+                }
+                if current_utf16_index == utf16_range.end {
+                    return Ok(start.unwrap()..unicode_index);
+                }
+
+                current_utf16_index += c.len_utf16();
+                unicode_index += 1;
+                for utf16_index in [utf16_range.start, utf16_range.end] {
+                    if current_utf16_index > utf16_index
+                        && current_utf16_index - c.len_utf16() < utf16_index
+                    {
+                        return Err(LoroError::ArgErr(
+                            format!(
+                                "UTF-16 index {} is in the middle of the surrogate pair of {:?}",
+                                utf16_index, c
+                            )
+                            .into_boxed_str(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if current_utf16_index == utf16_range.start {
+            start.get_or_insert(unicode_index);
+        }
+        if current_utf16_index != utf16_range.end {
+            return Err(LoroError::OutOfBound {
+                pos: utf16_range.end,
+                len: current_utf16_index,
+            });
+        }
+
+        Ok(start.unwrap()..unicode_index)
+    }
+
     pub(crate) fn decode_snapshot(
         &mut self,
         EncodedRichtextState {
@@ -543,6 +870,7 @@ impl RichtextState {
         arena: &SharedArena,
     ) {
         assert!(self.is_empty());
+        self.value_cache = None;
         if text_bytes.is_empty() {
             return;
         }
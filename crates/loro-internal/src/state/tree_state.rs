@@ -270,9 +270,12 @@ impl ContainerState for TreeState {
         self.in_txn = false;
     }
 
+    /// The nodes are sorted by id, so the value doesn't depend on the order of the updates.
     fn get_value(&mut self) -> LoroValue {
         let mut ans = vec![];
-        for (target, parent) in self.trees.iter() {
+        let mut nodes: Vec<_> = self.trees.iter().collect();
+        nodes.sort_unstable_by_key(|(target, _)| **target);
+        for (target, parent) in nodes {
             if !self.deleted.contains(target) && !TreeID::is_unexist_root(Some(*target)) {
                 let mut t = FxHashMap::default();
                 t.insert("id".to_string(), target.id().to_string().into());
@@ -291,12 +294,18 @@ impl ContainerState for TreeState {
     }
 
     /// Get the index of the child container
+    ///
+    /// Return None if the node of the meta container is deleted or unknown.
     fn get_child_index(&self, id: &ContainerID) -> Option<Index> {
         let id = id.as_normal().unwrap();
-        Some(Index::Node(TreeID {
+        let tree_id = TreeID {
             peer: *id.0,
             counter: *id.1,
-        }))
+        };
+        if !self.trees.contains_key(&tree_id) || self.is_deleted(&tree_id) {
+            return None;
+        }
+        Some(Index::Node(tree_id))
     }
 
     fn get_child_containers(&self) -> Vec<ContainerID> {
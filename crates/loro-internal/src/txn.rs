@@ -11,18 +11,22 @@ use rle::{HasLength, Mergable, RleVec};
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    change::{get_sys_timestamp, Change, Lamport, Timestamp},
+    change::{Change, Lamport, Timestamp},
     container::{
         idx::ContainerIdx,
-        list::list_op::{DeleteSpan, InnerListOp},
+        list::list_op::{DeleteSpan, InnerListOp, ListOp},
         richtext::{Style, StyleKey, TextStyleInfoFlag},
         IntoContainerId,
     },
     delta::{Delta, MapValue, StyleMeta, StyleMetaItem, TreeDiff, TreeDiffItem},
     event::Diff,
     id::{Counter, PeerID, ID},
-    op::{Op, RawOp, RawOpContent},
+    op::{InnerContent, ListSlice, Op, RawOp, RawOpContent},
     span::HasIdSpan,
+    utils::{
+        lock::{LockOrErr, LockOrRecover, Recover},
+        utf16::count_utf16_len,
+    },
     version::Frontiers,
     InternalString, LoroError, LoroValue,
 };
@@ -193,7 +197,7 @@ impl Transaction {
         origin: InternalString,
         global_txn: Weak<Mutex<Option<Transaction>>>,
     ) -> Self {
-        let mut state_lock = state.lock().unwrap();
+        let mut state_lock = state.lock_or_recover();
         if state_lock.is_in_txn() {
             panic!("Cannot start a transaction while another one is in progress");
         }
@@ -235,6 +239,13 @@ impl Transaction {
         self._commit()
     }
 
+    /// Use the given timestamp for this change instead of [`Configure::get_time`].
+    ///
+    /// Timestamps never go backwards: the change gets `max(time, latest timestamp in the oplog)`.
+    /// If the change is merged into the previous change of the same peer, the merged change
+    /// keeps the earliest timestamp, i.e. the one of the previous change.
+    ///
+    /// [`Configure::get_time`]: crate::configure::Configure::get_time
     pub fn set_timestamp(&mut self, time: Timestamp) {
         self.timestamp = Some(time);
     }
@@ -257,7 +268,7 @@ impl Transaction {
         }
 
         self.finished = true;
-        self.state.lock().unwrap().abort_txn();
+        self.state.lock_or_recover().abort_txn();
         self.local_ops.clear();
         self.event_hints.clear();
     }
@@ -268,14 +279,27 @@ impl Transaction {
         }
 
         self.finished = true;
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock_or_recover();
+        if !state.is_in_txn() {
+            self.local_ops.clear();
+            self.event_hints.clear();
+            return Err(rolled_back_err());
+        }
+
         if self.local_ops.is_empty() {
             state.abort_txn();
             return Ok(());
         }
 
         let ops = std::mem::take(&mut self.local_ops);
-        let mut oplog = self.oplog.lock().unwrap();
+        let mut oplog = match self.oplog.lock_or_err() {
+            Ok(oplog) => oplog,
+            Err(err) => {
+                state.abort_txn();
+                self.event_hints.clear();
+                return Err(err);
+            }
+        };
         let deps = take(&mut self.frontiers);
         let change = Change {
             lamport: self.start_lamport,
@@ -284,7 +308,7 @@ impl Transaction {
             id: ID::new(self.peer, self.start_counter),
             timestamp: oplog
                 .latest_timestamp
-                .max(self.timestamp.unwrap_or_else(get_sys_timestamp)),
+                .max(self.timestamp.unwrap_or_else(oplog.configure.get_time)),
             has_dependents: false,
         };
 
@@ -343,12 +367,11 @@ impl Transaction {
     ) -> LoroResult<()> {
         if Arc::as_ptr(&self.state) != Weak::as_ptr(state_ref) {
             return Err(LoroError::UnmatchedContext {
-                expected: self.state.lock().unwrap().peer,
-                found: state_ref.upgrade().unwrap().lock().unwrap().peer,
+                expected: self.state.lock_or_recover().peer,
+                found: state_ref.upgrade().unwrap().lock_or_recover().peer,
             });
         }
 
-        let len = content.content_len();
         let raw_op = RawOp {
             id: ID {
                 peer: self.peer,
@@ -359,10 +382,135 @@ impl Transaction {
             content,
         };
 
-        let mut state = self.state.lock().unwrap();
         let op = self.arena.convert_raw_op(&raw_op);
-        state.apply_local_op(&raw_op, &op)?;
-        drop(state);
+        self.push_local_op(&raw_op, op, event)
+    }
+
+    /// Apply a batch of local ops to `container` under a single lock of the state.
+    ///
+    /// `next_op` is called with the state of the container before each op, so the
+    /// state includes the previous ops of the batch. It returns the content and the
+    /// event hint of the next op, or `None` to end the batch.
+    pub(super) fn apply_local_ops<'a>(
+        &mut self,
+        container: ContainerIdx,
+        mut next_op: impl FnMut(&mut State) -> LoroResult<Option<(RawOpContent<'a>, EventHint)>>,
+        // check whether context and txn are referring to the same state context
+        state_ref: &Weak<Mutex<DocState>>,
+    ) -> LoroResult<()> {
+        if Arc::as_ptr(&self.state) != Weak::as_ptr(state_ref) {
+            return Err(LoroError::UnmatchedContext {
+                expected: self.state.lock_or_recover().peer,
+                found: state_ref.upgrade().unwrap().lock_or_recover().peer,
+            });
+        }
+
+        let state = self.state.clone();
+        let mut state = state.lock_or_recover();
+        while let Some((content, event)) = state.with_state_mut(container, &mut next_op)? {
+            let raw_op = RawOp {
+                id: ID {
+                    peer: self.peer,
+                    counter: self.next_counter,
+                },
+                lamport: self.next_lamport,
+                container,
+                content,
+            };
+            let op = self.arena.convert_raw_op(&raw_op);
+            self.push_local_op_to_state(&mut state, &raw_op, op, event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert the chars into the text without collecting them into a `String` first.
+    /// They are written into the arena directly.
+    ///
+    /// `entity_index` is the insert pos in the text state, while `event_pos` is the
+    /// pos in the event. If the chars are empty, it's a no-op.
+    pub(super) fn insert_text_chars(
+        &mut self,
+        container: ContainerIdx,
+        entity_index: usize,
+        chars: impl Iterator<Item = char>,
+        event_pos: usize,
+        styles: StyleMeta,
+        state_ref: &Weak<Mutex<DocState>>,
+    ) -> LoroResult<()> {
+        if Arc::as_ptr(&self.state) != Weak::as_ptr(state_ref) {
+            return Err(LoroError::UnmatchedContext {
+                expected: self.state.lock_or_recover().peer,
+                found: state_ref.upgrade().unwrap().lock_or_recover().peer,
+            });
+        }
+
+        let (slice, unicode_start, unicode_len) = self.arena.alloc_chars_with_slice(chars);
+        if unicode_len == 0 {
+            return Ok(());
+        }
+
+        let event_len = if cfg!(feature = "wasm") {
+            count_utf16_len(&slice)
+        } else {
+            unicode_len
+        };
+        let id = ID::new(self.peer, self.next_counter);
+        let op = Op::new(
+            id,
+            InnerContent::List(InnerListOp::InsertText {
+                slice: slice.clone(),
+                unicode_start: unicode_start as u32,
+                unicode_len: unicode_len as u32,
+                pos: entity_index as u32,
+            }),
+            container,
+        );
+        let raw_op = RawOp {
+            id,
+            lamport: self.next_lamport,
+            container,
+            content: RawOpContent::List(ListOp::Insert {
+                slice: ListSlice::RawStr {
+                    str: Cow::Borrowed(std::str::from_utf8(&slice).unwrap()),
+                    unicode_len,
+                },
+                pos: entity_index,
+            }),
+        };
+
+        self.push_local_op(
+            &raw_op,
+            op,
+            EventHint::InsertText {
+                pos: event_pos as u32,
+                event_len: event_len as u32,
+                unicode_len: unicode_len as u32,
+                styles,
+            },
+        )
+    }
+
+    fn push_local_op(&mut self, raw_op: &RawOp, op: Op, event: EventHint) -> LoroResult<()> {
+        let state = self.state.clone();
+        let mut state = state.lock_or_recover();
+        self.push_local_op_to_state(&mut state, raw_op, op, event)
+    }
+
+    /// Like [Self::push_local_op], but the state is locked by the caller.
+    fn push_local_op_to_state(
+        &mut self,
+        state: &mut DocState,
+        raw_op: &RawOp,
+        op: Op,
+        event: EventHint,
+    ) -> LoroResult<()> {
+        let len = op.atom_len();
+        if !state.is_in_txn() {
+            return Err(rolled_back_err());
+        }
+
+        state.apply_local_op(raw_op, &op)?;
         debug_assert_eq!(
             event.rle_len(),
             op.atom_len(),
@@ -418,7 +566,7 @@ impl Transaction {
     }
 
     pub fn get_value_by_idx(&self, idx: ContainerIdx) -> LoroValue {
-        self.state.lock().unwrap().get_value_by_idx(idx)
+        self.state.lock_or_recover().get_value_by_idx(idx)
     }
 
     #[allow(unused)]
@@ -426,7 +574,7 @@ impl Transaction {
     where
         F: FnOnce(&State) -> R,
     {
-        let state = self.state.lock().unwrap();
+        let state = self.state.lock_or_recover();
         f(state.get_state(idx).unwrap())
     }
 
@@ -440,11 +588,48 @@ impl Transaction {
     pub fn is_empty(&self) -> bool {
         self.local_ops.is_empty()
     }
+
+    /// Whether the doc state was recovered from a panic in this txn, which rolled
+    /// the txn back. Then it can only be aborted.
+    pub(crate) fn is_rolled_back(&self) -> bool {
+        !self.finished && !self.state.lock_or_recover().is_in_txn()
+    }
+}
+
+fn rolled_back_err() -> LoroError {
+    LoroError::TransactionError("The transaction was rolled back after a panic".into())
+}
+
+impl Recover for Option<Transaction> {
+    /// Replace the auto-commit txn, which may have been interrupted in the middle
+    /// of an op, with a new one. Its uncommitted edits are discarded.
+    ///
+    /// It's also used to replace an auto-commit txn that [Transaction::is_rolled_back].
+    fn recover(&mut self) {
+        if let Some(mut txn) = self.take() {
+            let on_commit = txn.take_on_commit();
+            txn._abort();
+            let mut new_txn = Transaction::new_with_origin(
+                txn.state.clone(),
+                txn.oplog.clone(),
+                txn.origin.clone(),
+                txn.global_txn.clone(),
+            );
+            if let Some(on_commit) = on_commit {
+                new_txn.set_on_commit(on_commit);
+            }
+
+            *self = Some(new_txn);
+        }
+    }
 }
 
 impl Drop for Transaction {
     fn drop(&mut self) {
-        if !self.finished {
+        if std::thread::panicking() || self.is_rolled_back() {
+            // the panic may have left the txn in the middle of an op
+            self._abort();
+        } else if !self.finished {
             // TODO: should we abort here or commit here?
             // what if commit fails?
             self._commit().unwrap();
@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, TryLockError},
+};
+
+use loro_common::{LoroError, LoroResult};
+
+pub(crate) trait LockOrRecover<T> {
+    /// Lock the mutex even if it's poisoned.
+    ///
+    /// A mutex is poisoned when a thread panicked while holding it. Then every
+    /// later `lock().unwrap()` would panic too, so a single failed operation
+    /// would take down the whole document. Instead we log a warning, clear the
+    /// poison and let the data restore its invariants with [Recover::recover].
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+
+    /// Like [LockOrRecover::lock_or_recover], but panic if the mutex is already locked,
+    /// which means it's reentered by the current thread.
+    fn try_lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+/// The data behind a mutex that can be used again after a panic while it was locked.
+pub(crate) trait Recover {
+    /// Restore the invariants that the panic may have broken.
+    ///
+    /// It's a no-op by default, for the data without any invariant across updates.
+    fn recover(&mut self) {}
+}
+
+impl<T: Recover> LockOrRecover<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock()
+            .unwrap_or_else(|err| recover(self, err.into_inner()))
+    }
+
+    fn try_lock_or_recover(&self) -> MutexGuard<'_, T> {
+        match self.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(err)) => recover(self, err.into_inner()),
+            Err(TryLockError::WouldBlock) => panic!("The lock is already held"),
+        }
+    }
+}
+
+fn recover<'a, T: Recover>(mutex: &Mutex<T>, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+    tracing::warn!("Recover from a poisoned lock");
+    mutex.clear_poison();
+    guard.recover();
+    guard
+}
+
+pub(crate) trait LockOrErr<T> {
+    /// Lock the mutex, or return [LoroError::LockError] if it's poisoned.
+    ///
+    /// It's for the data whose invariants can't be restored after a panic, e.g. an
+    /// [crate::OpLog] interrupted in the middle of an import. Reusing it could corrupt
+    /// the doc, so it fails instead. The methods that can't return an error panic.
+    fn lock_or_err(&self) -> LoroResult<MutexGuard<'_, T>>;
+}
+
+impl<T> LockOrErr<T> for Mutex<T> {
+    fn lock_or_err(&self) -> LoroResult<MutexGuard<'_, T>> {
+        self.lock().map_err(|_| {
+            tracing::error!("The lock is poisoned");
+            LoroError::LockError
+        })
+    }
+}
+
+impl<T> Recover for Vec<T> {}
+impl<K, V, S> Recover for HashMap<K, V, S> {}
@@ -1,4 +1,5 @@
 pub(crate) mod bitmap;
 pub(crate) mod lazy;
+pub(crate) mod lock;
 pub mod string_slice;
 pub(crate) mod utf16;
@@ -49,6 +49,10 @@ impl ImVersionVector {
         self.0.get(key)
     }
 
+    pub fn includes_id(&self, id: ID) -> bool {
+        self.get(&id.peer).is_some_and(|&end| end > id.counter)
+    }
+
     pub fn insert(&mut self, k: PeerID, v: Counter) {
         self.0.insert(k, v);
     }
@@ -183,6 +187,12 @@ impl From<Vec<ID>> for Frontiers {
     }
 }
 
+impl From<Frontiers> for Vec<ID> {
+    fn from(value: Frontiers) -> Self {
+        value.0.into_vec()
+    }
+}
+
 impl FromIterator<ID> for Frontiers {
     fn from_iter<I: IntoIterator<Item = ID>>(iter: I) -> Self {
         Self(iter.into_iter().collect())
@@ -599,6 +609,27 @@ impl VersionVector {
         }
     }
 
+    /// Whether `self` strictly dominates `other`, i.e. `self > other`.
+    ///
+    /// It's cheaper than [PartialOrd::partial_cmp] when only this direction matters,
+    /// because it returns as soon as it finds a peer where `self` is behind `other`.
+    pub fn dominates(&self, other: &VersionVector) -> bool {
+        let mut greater = false;
+        for (peer, &other_end) in other.iter() {
+            let self_end = self.get(peer).copied().unwrap_or(0);
+            if self_end < other_end {
+                return false;
+            }
+
+            greater |= self_end > other_end;
+        }
+
+        greater
+            || self
+                .iter()
+                .any(|(peer, &end)| end > 0 && !other.contains_key(peer))
+    }
+
     pub fn includes_id(&self, id: ID) -> bool {
         if let Some(end) = self.get(&id.peer) {
             if *end > id.counter {
@@ -698,6 +729,9 @@ impl VersionVector {
         }
     }
 
+    /// The peers present in both vectors, with the smaller counter of the two.
+    ///
+    /// Peers whose resulting counter is 0 are omitted.
     pub fn intersection(&self, other: &VersionVector) -> VersionVector {
         let mut ans = VersionVector::new();
         for (client_id, &counter) in self.iter() {
@@ -714,6 +748,13 @@ impl VersionVector {
         ans
     }
 
+    /// The peers present in either vector, with the larger counter of the two.
+    pub fn union(&self, other: &VersionVector) -> VersionVector {
+        let mut ans = self.clone();
+        ans.merge(other);
+        ans
+    }
+
     #[inline(always)]
     #[instrument(skip_all)]
     pub fn encode(&self) -> Vec<u8> {
@@ -1135,6 +1176,37 @@ mod tests {
             assert!(a < b);
             assert!(a <= b);
         }
+
+        #[test]
+        fn dominates() {
+            let pairs: [(Vec<ID>, Vec<ID>); 5] = [
+                (
+                    vec![ID::new(1, 1), ID::new(2, 2)],
+                    vec![ID::new(1, 1), ID::new(2, 2)],
+                ),
+                (
+                    vec![ID::new(1, 2), ID::new(2, 1)],
+                    vec![ID::new(1, 1), ID::new(2, 2)],
+                ),
+                (
+                    vec![ID::new(1, 2), ID::new(2, 3)],
+                    vec![ID::new(1, 1), ID::new(2, 2)],
+                ),
+                (vec![ID::new(1, 1), ID::new(2, 2)], vec![ID::new(1, 1)]),
+                (vec![ID::new(1, 1)], vec![]),
+            ];
+            for (a, b) in pairs {
+                let a: VersionVector = a.into();
+                let b: VersionVector = b.into();
+                assert_eq!(a.dominates(&b), a > b);
+                assert_eq!(b.dominates(&a), b > a);
+            }
+
+            let mut a = VersionVector::new();
+            a.insert(3, 0);
+            assert!(!a.dominates(&VersionVector::new()));
+            assert!(!VersionVector::new().dominates(&a));
+        }
     }
 
     #[test]
@@ -1151,6 +1223,55 @@ mod tests {
         assert_eq!(b.get(&2), Some(&3));
     }
 
+    #[test]
+    fn intersection_and_union() {
+        let mut a = VersionVector::new();
+        a.insert(1, 5);
+        a.insert(2, 3);
+        a.insert(3, 0);
+        let mut b = VersionVector::new();
+        b.insert(1, 2);
+        b.insert(2, 7);
+        b.insert(3, 4);
+        b.insert(4, 1);
+
+        let mut expected = VersionVector::new();
+        expected.insert(1, 2);
+        expected.insert(2, 3);
+        assert_eq!(a.intersection(&b), expected);
+        assert_eq!(b.intersection(&a), expected);
+
+        let mut expected = VersionVector::new();
+        expected.insert(1, 5);
+        expected.insert(2, 7);
+        expected.insert(3, 4);
+        expected.insert(4, 1);
+        assert_eq!(a.union(&b), expected);
+        assert_eq!(b.union(&a), expected);
+
+        // disjoint peers
+        let mut c = VersionVector::new();
+        c.insert(5, 1);
+        assert!(a.intersection(&c).is_empty());
+        let u = a.union(&c);
+        assert_eq!(u.len(), 4);
+        assert_eq!(u.get(&5), Some(&1));
+        assert_eq!(u.get(&1), Some(&5));
+    }
+
+    #[test]
+    fn frontiers_encode_decode() {
+        let empty = Frontiers::default();
+        assert_eq!(Frontiers::decode(&empty.encode()).unwrap(), empty);
+
+        let f: Frontiers = vec![ID::new(1, 0), ID::new(u64::MAX, 1000), ID::new(3, 7)].into();
+        let bytes = f.encode();
+        assert_eq!(Frontiers::decode(&bytes).unwrap(), f);
+
+        assert!(Frontiers::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Frontiers::decode(&[5]).is_err());
+    }
+
     #[test]
     fn field_order() {
         let tos = TotalOrderStamp {
@@ -1,9 +1,14 @@
 use std::sync::{atomic::AtomicBool, Arc, Mutex};
 
-use loro_common::{ContainerID, ContainerType, LoroValue, ID};
+use loro_common::{ContainerID, ContainerType, LoroError, LoroValue, PathSegment, ID};
 use loro_internal::{
-    container::richtext::TextStyleInfoFlag, version::Frontiers, ApplyDiff, LoroDoc, ToJson,
+    configure::Configure,
+    container::richtext::TextStyleInfoFlag,
+    event::{Diff, Index},
+    version::Frontiers,
+    ApplyDiff, FxHashMap, LoroDoc, ToJson,
 };
+use rle::HasLength;
 use serde_json::json;
 
 #[test]
@@ -39,6 +44,9 @@ fn out_of_bound_test() {
     assert!(matches!(err, loro_common::LoroError::OutOfBound { .. }));
     let err = a.get_text("text").delete_(3, 5).unwrap_err();
     assert!(matches!(err, loro_common::LoroError::OutOfBound { .. }));
+    let err = a.get_text("text").delete_(usize::MAX, 2).unwrap_err();
+    assert!(matches!(err, loro_common::LoroError::OutOfBound { .. }));
+    assert_eq!(a.get_text("text").get_value(), LoroValue::from("Hello"));
     let err = a
         .get_text("text")
         .mark_(0, 8, "h", 5.into(), TextStyleInfoFlag::BOLD)
@@ -64,6 +72,7 @@ fn list() {
     let a = LoroDoc::new_auto_commit();
     a.get_list("list").insert_(0, "Hello".into()).unwrap();
     assert_eq!(a.get_list("list").get(0).unwrap(), LoroValue::from("Hello"));
+    assert_eq!(a.get_list("list").get(1), None);
     let map = a
         .get_list("list")
         .insert_container_(1, ContainerType::Map)
@@ -681,3 +690,858 @@ fn tree_checkout() {
         })
         .unwrap();
 }
+
+#[test]
+fn preview_import() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    a.get_text("text").insert_(0, "hello").unwrap();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(2).unwrap();
+    b.import(&a.export_from(&Default::default())).unwrap();
+    b.get_text("text").insert_(5, " world").unwrap();
+    b.get_map("map").insert_("key", 1.into()).unwrap();
+    b.commit_then_renew();
+
+    let preview = a.preview_import(&b.export_from(&a.oplog_vv())).unwrap();
+    assert_eq!(
+        preview.value.to_json_value(),
+        json!({"text": "hello world", "map": {"key": 1}})
+    );
+    assert_eq!(preview.diff.len(), 2);
+    assert_eq!(a.get_deep_value().to_json_value(), json!({"text": "hello"}));
+
+    a.commit_preview(preview).unwrap();
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+
+    // the pending txn is neither committed nor included in the preview
+    a.get_map("map").insert_("pending", 2.into()).unwrap();
+    let vv = a.oplog_vv();
+    b.get_text("text").insert_(0, "> ").unwrap();
+    b.commit_then_renew();
+    let preview = a.preview_import(&b.export_from(&vv)).unwrap();
+    assert_eq!(
+        preview.value.to_json_value(),
+        json!({"text": "> hello world", "map": {"key": 1}})
+    );
+    assert_eq!(preview.diff.len(), 1);
+    assert_eq!(a.oplog_vv(), vv);
+    a.commit_then_renew();
+    assert_ne!(a.oplog_vv(), vv);
+}
+
+#[test]
+fn disable_merge_local_ops() {
+    let insert_10_times = |doc: &LoroDoc| {
+        doc.set_peer_id(1).unwrap();
+        let text = doc.get_text("text");
+        for i in 0..10 {
+            let mut txn = doc.txn().unwrap();
+            txn.set_timestamp(i);
+            text.insert(&mut txn, 0, "a").unwrap();
+            txn.commit().unwrap();
+        }
+    };
+
+    let doc = LoroDoc::new();
+    insert_10_times(&doc);
+    assert_eq!(doc.oplog().lock().unwrap().changes()[&1].len(), 1);
+
+    let doc = LoroDoc::new();
+    doc.set_configure(Configure {
+        merge_local_ops: false,
+        ..Default::default()
+    });
+    insert_10_times(&doc);
+    let oplog = doc.oplog().lock().unwrap();
+    let changes = &oplog.changes()[&1];
+    assert_eq!(changes.len(), 10);
+    assert!(changes.iter().map(|c| c.timestamp()).eq(0..10));
+}
+
+#[test]
+fn imported_changes_are_never_merged() {
+    let a = LoroDoc::new();
+    a.set_peer_id(1).unwrap();
+    a.set_configure(Configure {
+        merge_local_ops: false,
+        ..Default::default()
+    });
+    let text = a.get_text("text");
+    for i in 0..5 {
+        let mut txn = a.txn().unwrap();
+        txn.set_timestamp(i);
+        text.insert(&mut txn, 0, "a").unwrap();
+        txn.commit().unwrap();
+    }
+
+    // the receivers merge their own local ops, but keep the imported changes apart
+    let b = LoroDoc::new();
+    b.import(&a.export_from(&Default::default())).unwrap();
+    let c = LoroDoc::new();
+    c.import(&a.export_snapshot()).unwrap();
+    for doc in [&a, &b, &c] {
+        let oplog = doc.oplog().lock().unwrap();
+        let changes = &oplog.changes()[&1];
+        assert_eq!(changes.len(), 5);
+        assert!(changes.iter().map(|c| c.timestamp()).eq(0..5));
+    }
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+    assert_eq!(a.get_deep_value(), c.get_deep_value());
+}
+
+#[test]
+fn custom_timestamp() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    for time in [100, 50, 120] {
+        let mut txn = doc.txn().unwrap();
+        txn.set_timestamp(time);
+        text.insert(&mut txn, 0, "a").unwrap();
+        txn.commit().unwrap();
+    }
+    // merged into one change that keeps the earliest timestamp
+    let oplog = doc.oplog().lock().unwrap();
+    let changes = &oplog.changes()[&1];
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].timestamp(), 100);
+    drop(oplog);
+
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    doc.set_configure(Configure {
+        merge_local_ops: false,
+        ..Default::default()
+    });
+    let text = doc.get_text("text");
+    for time in [100, 50, 120] {
+        let mut txn = doc.txn().unwrap();
+        txn.set_timestamp(time);
+        text.insert(&mut txn, 0, "a").unwrap();
+        txn.commit().unwrap();
+    }
+    // timestamps never go backwards
+    let oplog = doc.oplog().lock().unwrap();
+    let timestamps: Vec<_> = oplog.changes()[&1].iter().map(|c| c.timestamp()).collect();
+    assert_eq!(timestamps, vec![100, 100, 120]);
+}
+
+#[test]
+fn changes_since_timestamp() {
+    let a = LoroDoc::new();
+    a.set_peer_id(1).unwrap();
+    a.set_configure(Configure {
+        merge_local_ops: false,
+        ..Default::default()
+    });
+    let b = LoroDoc::new();
+    b.set_peer_id(2).unwrap();
+    let text = a.get_text("text");
+    let list = b.get_list("list");
+    for i in 0..5 {
+        let mut txn = a.txn().unwrap();
+        txn.set_timestamp(i * 2);
+        text.insert(&mut txn, 0, "a").unwrap();
+        txn.commit().unwrap();
+        let mut txn = b.txn().unwrap();
+        txn.set_timestamp(i * 2 + 1);
+        list.push(&mut txn, (i as i32).into()).unwrap();
+        txn.commit().unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        b.import(&a.export_from(&b.oplog_vv())).unwrap();
+    }
+
+    let oplog = a.oplog().lock().unwrap();
+    let changes = oplog.changes_since_timestamp(4);
+    let mut timestamps: Vec<_> = changes.iter().map(|c| c.timestamp).collect();
+    timestamps.sort();
+    assert_eq!(timestamps, vec![5, 6, 7, 8, 9]);
+    // dependencies come before their dependents
+    for (i, change) in changes.iter().enumerate() {
+        for dep in change.deps.iter() {
+            assert!(!changes[i..]
+                .iter()
+                .any(|c| c.id.peer == dep.peer && c.id.counter <= dep.counter));
+        }
+    }
+
+    assert_eq!(oplog.changes_since_timestamp(9).len(), 0);
+    assert_eq!(oplog.changes_since_timestamp(-1).len(), 10);
+}
+
+#[test]
+fn on_local_ops() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_cloned = received.clone();
+    doc.oplog()
+        .lock()
+        .unwrap()
+        .on_local_ops(move |ops, change| {
+            received_cloned
+                .lock()
+                .unwrap()
+                .push((change.id, ops.len(), change.atom_len()));
+        });
+    let text = doc.get_text("text");
+    let map = doc.get_map("map");
+    doc.with_txn(|txn| {
+        text.insert(txn, 0, "abc")?;
+        map.insert(txn, "key", 1.into())
+    })
+    .unwrap();
+    doc.with_txn(|txn| text.insert(txn, 0, "de")).unwrap();
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![(ID::new(1, 0), 2, 4), (ID::new(1, 4), 1, 2)]
+    );
+
+    // imported changes are not local
+    let other = LoroDoc::new();
+    other.set_peer_id(2).unwrap();
+    other
+        .with_txn(|txn| other.get_text("text").insert(txn, 0, "x"))
+        .unwrap();
+    doc.import(&other.export_from(&Default::default())).unwrap();
+    assert_eq!(received.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn export_stream() {
+    let a = LoroDoc::new();
+    a.set_peer_id(1).unwrap();
+    let b = LoroDoc::new();
+    b.set_peer_id(2).unwrap();
+    let c = LoroDoc::new();
+    for i in 0..10 {
+        let text = a.get_text("text");
+        a.with_txn(|txn| text.insert(txn, 0, &i.to_string()))
+            .unwrap();
+        let list = b.get_list("list");
+        b.with_txn(|txn| list.push(txn, i.into())).unwrap();
+        a.import(&b.export_from(&a.oplog_vv())).unwrap();
+        b.import(&a.export_from(&b.oplog_vv())).unwrap();
+        if i == 4 {
+            c.import(&a.export_from(&Default::default())).unwrap();
+        }
+    }
+
+    let c_vv = c.oplog_vv();
+    let oplog = a.oplog().lock().unwrap();
+    for (doc, from) in [(LoroDoc::new(), Default::default()), (c, c_vv)] {
+        let mut last_vv = from.clone();
+        let mut chunks = 0;
+        for chunk in oplog.export_stream(&from, 3) {
+            doc.import(&chunk).unwrap();
+            // every chunk can be applied right away
+            let vv = doc.oplog_vv();
+            assert!(vv.includes_vv(&last_vv) && vv != last_vv);
+            last_vv = vv;
+            chunks += 1;
+        }
+
+        assert!(chunks > 1);
+        assert_eq!(doc.get_deep_value(), a.get_deep_value());
+    }
+}
+
+#[test]
+fn typing_trace_encoding_is_compact() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    let mut pos = 0;
+    for i in 0..1000 {
+        doc.with_txn(|txn| text.insert(txn, pos, "a")).unwrap();
+        pos += 1;
+        if i % 10 == 0 {
+            // move the cursor, so the next insert starts a new op
+            pos = (pos * 7) % (i + 2);
+        }
+    }
+
+    // The container and pos of the ops are delta-rle encoded, so the ~100 ops
+    // cost only a few bytes each on top of the inserted text
+    let encoded = doc.export_from(&Default::default());
+    assert!(encoded.len() < 1000 + 5 * 100, "{}", encoded.len());
+    let doc_b = LoroDoc::new();
+    doc_b.import(&encoded).unwrap();
+    assert_eq!(doc_b.get_deep_value(), doc.get_deep_value());
+}
+
+#[test]
+fn concurrent_inserts_at_same_pos_are_ordered_by_peer() {
+    for (peer_a, peer_b) in [(1, 2), (2, 1)] {
+        let a = LoroDoc::new();
+        a.set_peer_id(peer_a).unwrap();
+        let b = LoroDoc::new();
+        b.set_peer_id(peer_b).unwrap();
+        for (doc, s) in [(&a, "a"), (&b, "b")] {
+            let text = doc.get_text("text");
+            let list = doc.get_list("list");
+            doc.with_txn(|txn| {
+                text.insert(txn, 0, s)?;
+                list.insert(txn, 0, s.into())
+            })
+            .unwrap();
+        }
+
+        a.import(&b.export_from(&Default::default())).unwrap();
+        b.import(&a.export_from(&Default::default())).unwrap();
+        // the insertion of the smaller peer is on the left
+        let expected = if peer_a < peer_b {
+            json!({"text": "ab", "list": ["a", "b"]})
+        } else {
+            json!({"text": "ba", "list": ["b", "a"]})
+        };
+        assert_eq!(a.get_deep_value().to_json_value(), expected);
+        assert_eq!(b.get_deep_value().to_json_value(), expected);
+    }
+}
+
+#[test]
+fn import_linear_history() {
+    let single_writer = Configure {
+        single_writer: true,
+        ..Default::default()
+    };
+    let a = LoroDoc::new();
+    a.set_peer_id(1).unwrap();
+    a.set_configure(single_writer.clone());
+    let b = LoroDoc::new();
+    b.set_peer_id(2).unwrap();
+    b.set_configure(single_writer);
+    let text = a.get_text("text");
+    let list = a.get_list("list");
+    let map = a.get_map("map");
+    let tree = a.get_tree("tree");
+    let mut root = None;
+    for i in 0..20 {
+        let version = a.oplog_vv();
+        a.with_txn(|txn| {
+            text.insert(txn, i % (text.len_unicode() + 1), "ab")?;
+            if i % 3 == 0 {
+                text.delete(txn, 0, 1)?;
+                text.mark(txn, 0, 1, "bold", true.into(), TextStyleInfoFlag::BOLD)?;
+            }
+            list.insert(txn, 0, (i as i32).into())?;
+            map.insert(txn, "key", (i as i32).into())?;
+            let node = tree.create(txn)?;
+            match root {
+                Some(root) => tree.mov(txn, node, root)?,
+                None => root = Some(node),
+            }
+            if i % 4 == 0 {
+                let sub = map.insert_container(txn, &i.to_string(), ContainerType::Text)?;
+                sub.as_text().unwrap().insert(txn, 0, "sub")?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        // every update is a linear extension of b
+        b.import(&a.export_from(&version)).unwrap();
+        assert_eq!(b.get_deep_value(), a.get_deep_value());
+        assert_eq!(
+            b.get_text("text").get_richtext_value(),
+            text.get_richtext_value()
+        );
+    }
+
+    // b can still edit and sync with a, the concurrent updates fall back to the tracker
+    let b_text = b.get_text("text");
+    b.with_txn(|txn| b_text.insert(txn, 0, "x")).unwrap();
+    a.with_txn(|txn| text.insert(txn, 0, "y")).unwrap();
+    a.import(&b.export_from(&a.oplog_vv())).unwrap();
+    b.import(&a.export_from(&b.oplog_vv())).unwrap();
+    assert_eq!(b.get_deep_value(), a.get_deep_value());
+}
+
+#[test]
+fn export_to_frontier() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(2).unwrap();
+    b.get_list("list").insert_(0, 1.into()).unwrap();
+    b.commit_then_renew();
+    a.import(&b.export_from(&a.oplog_vv())).unwrap();
+    a.get_text("text").insert_(0, "hello").unwrap();
+    a.commit_then_renew();
+    let bookmark = a.oplog_frontiers();
+    a.get_text("text").insert_(5, " world").unwrap();
+    a.get_list("list").insert_(1, 2.into()).unwrap();
+    a.commit_then_renew();
+
+    let c = LoroDoc::new_auto_commit();
+    c.import(&a.export_to_frontier(&c.oplog_vv(), &bookmark).unwrap())
+        .unwrap();
+    assert_eq!(c.oplog_frontiers(), bookmark);
+    assert_eq!(
+        c.get_deep_value().to_json_value(),
+        json!({"text": "hello", "list": [1]})
+    );
+
+    // the target can be in the middle of a change, and `from` skips what the peer already has
+    let mid = Frontiers::from(ID::new(1, 2));
+    b.import(&a.export_to_frontier(&b.oplog_vv(), &mid).unwrap())
+        .unwrap();
+    assert_eq!(b.oplog_frontiers(), mid);
+    assert_eq!(
+        b.get_text("text").get_value().as_string().unwrap().as_str(),
+        "hel"
+    );
+
+    assert!(a
+        .export_to_frontier(&Default::default(), &ID::new(3, 0).into())
+        .is_err());
+}
+
+#[test]
+fn diff_between_versions() {
+    let doc = LoroDoc::new_auto_commit();
+    doc.set_peer_id(1).unwrap();
+    doc.get_text("text").insert_(0, "hello").unwrap();
+    doc.get_list("list").insert_(0, 1.into()).unwrap();
+    doc.commit_then_renew();
+    let v1 = doc.oplog_vv();
+    let value1 = doc.get_deep_value();
+    doc.get_text("text").insert_(5, " world").unwrap();
+    doc.get_text("text").delete_(0, 1).unwrap();
+    doc.get_list("list").insert_(1, 2.into()).unwrap();
+    doc.get_map("map").insert_("key", "value".into()).unwrap();
+    doc.commit_then_renew();
+    let v2 = doc.oplog_vv();
+    let value2 = doc.get_deep_value();
+
+    let apply = |value: &LoroValue, diffs: &FxHashMap<ContainerID, Vec<Diff>>| {
+        let mut value = value.clone();
+        for (id, diff) in diffs {
+            let ContainerID::Root { name, .. } = id else {
+                unreachable!()
+            };
+            value.apply(&[Index::Key(name.clone())].into_iter().collect(), diff);
+        }
+        value
+    };
+
+    let forward = doc.diff(&v1, &v2).unwrap();
+    assert_eq!(forward.len(), 3);
+    assert_eq!(apply(&value1, &forward), value2);
+    let backward = doc.diff(&v2, &v1).unwrap();
+    assert_eq!(
+        apply(&value2, &backward).to_json_value(),
+        json!({"text": "hello", "list": [1], "map": {}})
+    );
+    assert!(doc.diff(&v2, &v2).unwrap().is_empty());
+    // the doc itself is not changed
+    assert_eq!(doc.get_deep_value(), value2);
+    assert!(!doc.is_detached());
+    // the pending txn is not committed
+    doc.get_text("text").insert_(0, "!").unwrap();
+    assert_eq!(apply(&value1, &doc.diff(&v1, &v2).unwrap()), value2);
+    assert_eq!(doc.oplog_vv(), v2);
+    doc.commit_then_renew();
+    // a version that misses the deps of its changes is rejected
+    let mut v3 = v1.clone();
+    v3.insert(1, v2.get(&1).unwrap() - 1);
+    v3.insert(2, 1);
+    let other = LoroDoc::new_auto_commit();
+    other.set_peer_id(2).unwrap();
+    other.import(&doc.export_from(&Default::default())).unwrap();
+    other.get_map("map").insert_("other", 1.into()).unwrap();
+    other.commit_then_renew();
+    doc.import(&other.export_from(&doc.oplog_vv())).unwrap();
+    assert!(matches!(doc.diff(&v1, &v3), Err(LoroError::ArgErr(_))));
+}
+
+#[test]
+fn import_truncated_bytes() {
+    let doc = LoroDoc::new_auto_commit();
+    let text = doc.get_text("text");
+    let mut exported = Vec::new();
+    for n in [10, 1000, 30_000] {
+        text.insert_(0, &"a".repeat(n)).unwrap();
+        doc.commit_then_renew();
+        exported.push(doc.export_from(&Default::default()));
+    }
+    exported.push(doc.export_snapshot());
+    for bytes in exported {
+        let step = (bytes.len() / 200).max(1);
+        for len in (0..bytes.len()).step_by(step) {
+            let new_doc = LoroDoc::new();
+            assert!(
+                new_doc.import(&bytes[..len]).is_err(),
+                "len={} total={}",
+                len,
+                bytes.len()
+            );
+        }
+    }
+}
+
+#[test]
+fn import_corrupted_bytes() {
+    let doc = LoroDoc::new_auto_commit();
+    let text = doc.get_text("text");
+    let mut exported = Vec::new();
+    for n in [10, 1000, 30_000] {
+        text.insert_(0, &"a".repeat(n)).unwrap();
+        doc.commit_then_renew();
+        exported.push(doc.export_from(&Default::default()));
+    }
+    exported.push(doc.export_snapshot());
+    for bytes in exported {
+        let mut corrupted = bytes.clone();
+        let mid = corrupted.len() / 2;
+        corrupted[mid] ^= 1;
+        let err = LoroDoc::new().import(&corrupted).unwrap_err();
+        assert!(matches!(err, loro_common::LoroError::Corrupted));
+
+        // the data exported without the checksum can still be imported
+        let mut legacy = bytes[..bytes.len() - 4].to_vec();
+        legacy[5] &= !0x80;
+        let new_doc = LoroDoc::new();
+        new_doc.import(&legacy).unwrap();
+        let expected = LoroDoc::new();
+        expected.import(&bytes).unwrap();
+        assert_eq!(new_doc.get_deep_value(), expected.get_deep_value());
+    }
+}
+
+#[test]
+fn fingerprint() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    a.get_text("text").insert_(0, "hello").unwrap();
+    a.get_map("map").insert_("a", 1.into()).unwrap();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(2).unwrap();
+    b.get_text("text").insert_(0, "world").unwrap();
+    b.get_map("map").insert_("b", 2.into()).unwrap();
+    b.get_list("list").insert_(0, "x".into()).unwrap();
+    let a_updates = a.export_from(&Default::default());
+    let b_updates = b.export_from(&Default::default());
+
+    let c = LoroDoc::new_auto_commit();
+    c.import(&a_updates).unwrap();
+    c.import(&b_updates).unwrap();
+    let d = LoroDoc::new_auto_commit();
+    d.import(&b_updates).unwrap();
+    d.import(&a_updates).unwrap();
+    // fetching an empty root container doesn't change the fingerprint
+    d.get_text("unused");
+    assert_eq!(c.oplog_vv(), d.oplog_vv());
+    assert_eq!(c.fingerprint(), d.fingerprint());
+    assert!(d.check_fingerprint(c.fingerprint()));
+
+    d.get_map("map").insert_("c", 3.into()).unwrap();
+    assert!(!c.check_fingerprint(d.fingerprint()));
+}
+
+#[test]
+fn fingerprint_of_tree() {
+    let docs: Vec<_> = (0..3)
+        .map(|peer| {
+            let doc = LoroDoc::new_auto_commit();
+            doc.set_peer_id(peer).unwrap();
+            doc
+        })
+        .collect();
+    // synced incrementally
+    let a = &docs[1];
+    for i in 0..34 {
+        for doc in docs.iter() {
+            doc.get_tree("tree").create_().unwrap();
+        }
+        if i % 2 == 0 {
+            a.import(&docs[0].export_from(&a.oplog_vv())).unwrap();
+            a.import(&docs[2].export_from(&a.oplog_vv())).unwrap();
+        }
+    }
+    a.import(&docs[0].export_from(&a.oplog_vv())).unwrap();
+    a.import(&docs[2].export_from(&a.oplog_vv())).unwrap();
+
+    // loaded from a snapshot, the nodes are inserted into the state in another order
+    let b = LoroDoc::new_auto_commit();
+    b.import(&a.export_snapshot()).unwrap();
+    // imported in another order
+    let c = LoroDoc::new_auto_commit();
+    for doc in docs.iter().rev() {
+        c.import(&doc.export_from(&Default::default())).unwrap();
+    }
+    assert_eq!(a.oplog_vv(), b.oplog_vv());
+    assert_eq!(a.oplog_vv(), c.oplog_vv());
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint(), c.fingerprint());
+}
+
+#[test]
+fn delete_container() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    let root = a.get_map("root");
+    let nested = root
+        .insert_container_("nested", ContainerType::Map)
+        .unwrap()
+        .into_map()
+        .unwrap();
+    let list = nested
+        .insert_container_("list", ContainerType::List)
+        .unwrap()
+        .into_list()
+        .unwrap();
+    list.insert_(0, 1.into()).unwrap();
+    root.insert_("k", 1.into()).unwrap();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(2).unwrap();
+    b.import(&a.export_from(&Default::default())).unwrap();
+
+    assert!(a.container_ids().contains(&nested.id()));
+    a.delete_container_(&nested.id()).unwrap();
+    assert_eq!(
+        a.get_deep_value().to_json_value(),
+        json!({"root": {"k": 1}})
+    );
+    // the descendants are deleted too
+    let ids = a.container_ids();
+    assert!(!ids.contains(&nested.id()));
+    assert!(!ids.contains(&list.id()));
+    assert!(ids.contains(&root.id()));
+    // already deleted
+    a.delete_container_(&nested.id()).unwrap();
+    assert!(matches!(
+        a.delete_container_(&root.id()),
+        Err(LoroError::ArgErr(_))
+    ));
+
+    // concurrent edits to the deleted container stay invisible
+    b.get_list(list.id()).insert_(1, 2.into()).unwrap();
+    a.import(&b.export_from(&a.oplog_vv())).unwrap();
+    b.import(&a.export_from(&b.oplog_vv())).unwrap();
+    assert_eq!(
+        a.get_deep_value().to_json_value(),
+        json!({"root": {"k": 1}})
+    );
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+
+    // the meta of a tree node can't be deleted without the node
+    let tree = a.get_tree("tree");
+    let node = tree.create_().unwrap();
+    let meta = tree.get_meta(node).unwrap();
+    assert!(matches!(
+        a.delete_container_(&meta.id()),
+        Err(LoroError::ArgErr(_))
+    ));
+    assert!(tree.contains(node));
+    assert!(a.container_ids().contains(&meta.id()));
+    tree.delete_(node).unwrap();
+    assert!(!a.container_ids().contains(&meta.id()));
+
+    // a container that is not attached to the doc
+    let detached = a.get_map(ContainerID::new_normal(ID::new(9, 0), ContainerType::Map));
+    assert!(matches!(
+        a.delete_container_(&detached.id()),
+        Err(LoroError::NotFoundError(_))
+    ));
+    assert!(!a.container_ids().contains(&detached.id()));
+}
+
+#[test]
+fn delete_container_in_txn() {
+    let doc = LoroDoc::new();
+    let root = doc.get_map("root");
+    let mut txn = doc.txn().unwrap();
+    let nested = root
+        .insert_container(&mut txn, "nested", ContainerType::List)
+        .unwrap()
+        .into_list()
+        .unwrap();
+    root.insert(&mut txn, "k", 1.into()).unwrap();
+    doc.delete_container(&mut txn, &nested.id()).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"root": {"k": 1}})
+    );
+}
+
+#[test]
+fn serde_loro_value() {
+    let doc = LoroDoc::new_auto_commit();
+    let map = doc.get_map("map");
+    map.insert_("str", "a".into()).unwrap();
+    map.insert_("num", 1.5.into()).unwrap();
+    let list = map
+        .insert_container_("list", ContainerType::List)
+        .unwrap()
+        .into_list()
+        .unwrap();
+    list.insert_(0, 1.into()).unwrap();
+    list.insert_(1, LoroValue::Null).unwrap();
+    list.insert_container_(2, ContainerType::Map)
+        .unwrap()
+        .into_map()
+        .unwrap()
+        .insert_("b", true.into())
+        .unwrap();
+
+    let value = doc.get_deep_value();
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(serde_json::from_str::<LoroValue>(&json).unwrap(), value);
+
+    // containers only keep their variant in non human-readable formats
+    let value = map.get_value();
+    let bytes = postcard::to_allocvec(&value).unwrap();
+    assert_eq!(postcard::from_bytes::<LoroValue>(&bytes).unwrap(), value);
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(
+        json["list"],
+        json!({ "Container": serde_json::to_value(list.id()).unwrap() })
+    );
+}
+
+#[test]
+fn text_arena_stats() {
+    let doc = LoroDoc::new_auto_commit();
+    assert_eq!(doc.text_arena_stats(), (0, 0));
+    let text = doc.get_text("text");
+    text.insert_(0, "hello world").unwrap();
+    doc.get_text("other").insert_(0, "abc").unwrap();
+    text.delete_(5, 6).unwrap();
+    doc.get_map("map").insert_("key", "value".into()).unwrap();
+    assert_eq!(doc.text_arena_stats(), (14, 8));
+}
+
+#[test]
+fn remote_text_insert_event() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    a.get_text("text").insert_(0, "hello").unwrap();
+    a.commit_then_renew();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(2).unwrap();
+    b.import(&a.export_snapshot()).unwrap();
+    b.get_text("text").insert_(5, "XY").unwrap();
+    b.commit_then_renew();
+
+    let ran = Arc::new(AtomicBool::new(false));
+    let ran_clone = ran.clone();
+    a.subscribe(
+        &a.get_text("text").id(),
+        Arc::new(move |e| {
+            let delta = e.container.diff.as_text().unwrap();
+            assert_eq!(
+                delta.to_json_value(),
+                json!([{"retain": 5}, {"insert": "XY"}])
+            );
+            ran_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+        }),
+    );
+    a.import(&b.export_from(&a.oplog_vv())).unwrap();
+    assert!(ran.load(std::sync::atomic::Ordering::Relaxed));
+}
+
+#[test]
+fn get_value_by_path() {
+    let doc = LoroDoc::new_auto_commit();
+    let settings = doc
+        .get_map("root")
+        .insert_container_("settings", ContainerType::Map)
+        .unwrap()
+        .into_map()
+        .unwrap();
+    settings.insert_("theme", "dark".into()).unwrap();
+    let list = doc.get_list("list");
+    list.insert_(0, 1.into()).unwrap();
+    list.insert_container_(1, ContainerType::Text)
+        .unwrap()
+        .into_text()
+        .unwrap()
+        .insert_(0, "hi")
+        .unwrap();
+    let value = doc.get_deep_value();
+
+    let path = |segments: Vec<PathSegment>| value.get_by_path(&segments).cloned();
+    assert_eq!(
+        path(vec!["root".into(), "settings".into(), "theme".into()]),
+        Some("dark".into())
+    );
+    assert_eq!(path(vec!["list".into(), 1.into()]), Some("hi".into()));
+    assert_eq!(path(vec![]), Some(value.clone()));
+    assert_eq!(path(vec!["list".into(), 2.into()]), None);
+    assert_eq!(path(vec!["list".into(), "theme".into()]), None);
+    assert_eq!(path(vec!["root".into(), 0.into()]), None);
+}
+
+#[test]
+fn container_ids() {
+    let doc = LoroDoc::new_auto_commit();
+    let map = doc.get_map("map");
+    let list = map
+        .insert_container_("list", ContainerType::List)
+        .unwrap()
+        .into_list()
+        .unwrap();
+    let text = list
+        .insert_container_(0, ContainerType::Text)
+        .unwrap()
+        .into_text()
+        .unwrap();
+    doc.commit_then_renew();
+    let ids = doc.container_ids();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.contains(&ContainerID::new_root("map", ContainerType::Map)));
+    assert!(ids.contains(&list.id()));
+    assert!(ids.contains(&text.id()));
+}
+
+#[test]
+fn change_peer_id_on_collision() {
+    let a = LoroDoc::new_auto_commit();
+    a.set_peer_id(1).unwrap();
+    a.get_text("text").insert_(0, "a").unwrap();
+    a.commit_then_renew();
+    let b = LoroDoc::new_auto_commit();
+    b.set_peer_id(1).unwrap();
+    b.import(&a.export_snapshot()).unwrap();
+    assert!(b.oplog_vv().get(&b.peer_id()).is_some());
+    b.set_peer_id(2).unwrap();
+    b.get_text("text").insert_(1, "b").unwrap();
+    b.commit_then_renew();
+    a.import(&b.export_from(&a.oplog_vv())).unwrap();
+    assert_eq!(a.get_deep_value().to_json_value(), json!({"text": "ab"}));
+    assert_eq!(b.oplog_vv().get(&2), Some(&1));
+}
+
+#[test]
+fn checkout_to_unknown_version() {
+    let mut doc = LoroDoc::new_auto_commit();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    text.insert_(0, "abc").unwrap();
+    doc.commit_then_renew();
+    assert!(doc
+        .checkout(&Frontiers::from(vec![ID::new(1, 10)]))
+        .is_err());
+    assert!(doc.checkout(&Frontiers::from(vec![ID::new(2, 0)])).is_err());
+    // the doc is still attached and editable
+    assert!(!doc.is_detached());
+    text.insert_(3, "d").unwrap();
+    doc.commit_then_renew();
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"text": "abcd"})
+    );
+
+    doc.checkout(&Frontiers::from(vec![ID::new(1, 1)])).unwrap();
+    assert_eq!(doc.get_deep_value().to_json_value(), json!({"text": "ab"}));
+    doc.checkout_to_latest();
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        json!({"text": "abcd"})
+    );
+}
@@ -53,24 +53,22 @@ impl<'a> FinalPhase<'a> {
     #[inline(always)]
     pub fn decode(bytes: &'a [u8]) -> Result<Self, LoroError> {
         let mut index = 0;
-        let len = leb::read_unsigned(bytes, &mut index) as usize;
-        let common = &bytes[index..index + len];
-        index += len;
-
-        let len = leb::read_unsigned(bytes, &mut index) as usize;
-        let app_state = &bytes[index..index + len];
-        index += len;
-
-        let len = leb::read_unsigned(bytes, &mut index) as usize;
-        let state_arena = &bytes[index..index + len];
-        index += len;
-
-        let len = leb::read_unsigned(bytes, &mut index) as usize;
-        let additional_arena = &bytes[index..index + len];
-        index += len;
-
-        let len = leb::read_unsigned(bytes, &mut index) as usize;
-        let oplog = &bytes[index..index + len];
+        let mut read_next = || -> Result<&'a [u8], LoroError> {
+            let len = leb::read_unsigned(bytes, &mut index)
+                .ok_or_else(|| LoroError::DecodeError("Invalid length".into()))?
+                as usize;
+            let ans = bytes
+                .get(index..index.saturating_add(len))
+                .ok_or_else(|| LoroError::DecodeError("Unexpected end of input".into()))?;
+            index += len;
+            Ok(ans)
+        };
+
+        let common = read_next()?;
+        let app_state = read_next()?;
+        let state_arena = read_next()?;
+        let additional_arena = read_next()?;
+        let oplog = read_next()?;
 
         Ok(FinalPhase {
             common: Cow::Borrowed(common),
@@ -285,28 +283,24 @@ mod leb {
         low_bits_of_byte(byte as u8)
     }
 
-    pub fn read_unsigned(r: &[u8], index: &mut usize) -> u64 {
+    /// Return None if the input ends unexpectedly or the value overflows u64.
+    pub fn read_unsigned(r: &[u8], index: &mut usize) -> Option<u64> {
         let mut result = 0;
         let mut shift = 0;
 
         loop {
-            let mut buf = [r[*index]];
+            let byte = *r.get(*index)?;
             *index += 1;
 
-            if shift == 63 && buf[0] != 0x00 && buf[0] != 0x01 {
-                while buf[0] & CONTINUATION_BIT != 0 {
-                    buf = [r[*index]];
-                    *index += 1;
-                }
-
-                panic!("overflow");
+            if shift == 63 && byte != 0x00 && byte != 0x01 {
+                return None;
             }
 
-            let low_bits = low_bits_of_byte(buf[0]) as u64;
+            let low_bits = low_bits_of_byte(byte) as u64;
             result |= low_bits << shift;
 
-            if buf[0] & CONTINUATION_BIT == 0 {
-                return result;
+            if byte & CONTINUATION_BIT == 0 {
+                return Some(result);
             }
 
             shift += 7;
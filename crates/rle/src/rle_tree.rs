@@ -30,6 +30,19 @@ pub struct RleTree<T: Rle + 'static, A: RleTreeTrait<T> + 'static> {
     pub node: <A::Arena as arena::Arena>::Boxed<'this, Node<'this, T, A>>,
 }
 
+/// See [`RleTree::stats`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeStats<I> {
+    /// The total atom length
+    pub len: I,
+    pub depth: usize,
+    pub internal_node_num: usize,
+    pub leaf_node_num: usize,
+    pub elem_num: usize,
+    /// Average number of elements in a leaf
+    pub avg_leaf_children: f64,
+}
+
 // SAFETY: tree is safe to send to another thread
 unsafe impl<T: Rle + 'static + Send, A: RleTreeTrait<T> + 'static> Send for RleTree<T, A> {}
 // SAFETY: &tree is safe to be shared between threads
@@ -106,6 +119,90 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         })
     }
 
+    /// Append the elements to the end of the tree.
+    ///
+    /// The elements are pushed to the last leaf directly and the leaf is split
+    /// when it's full, so building a tree this way is close to linear.
+    ///
+    /// `notify` would be invoke if a new element is inserted/moved to a new leaf node.
+    pub fn extend_notify<I, F>(&mut self, iter: I, notify: &mut F)
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&T, *mut LeafNode<'_, T, A>),
+    {
+        let mut iter = iter.into_iter();
+        if self.with_node(|node| node.as_internal().unwrap().children.is_empty()) {
+            let Some(first) = iter.next() else {
+                return;
+            };
+
+            self.insert_notify(A::Int::from_u8(0).unwrap(), first, notify);
+        }
+
+        let mut leaf: NonNull<LeafNode<'_, T, A>> =
+            self.root_mut().get_last_leaf_mut().unwrap().into();
+        for value in iter {
+            // SAFETY: we have exclusive ref to the tree and `leaf` is its last leaf
+            let result = unsafe { leaf.as_mut() }.push_child(value, notify);
+            if let Err(mut new) = result {
+                let new_leaf = new.as_leaf_mut().unwrap().into();
+                // SAFETY: `leaf` is valid and `new` is the leaf split from it
+                unsafe {
+                    Self::insert_leaf_after(leaf, new);
+                    Self::update_ancestors_cache(new_leaf);
+                }
+                leaf = new_leaf;
+            }
+        }
+
+        // SAFETY: `leaf` is the valid last leaf of the tree
+        unsafe { Self::update_ancestors_cache(leaf) };
+    }
+
+    /// Insert `new` into the parent of `leaf` right after it, splitting the ancestors if needed.
+    ///
+    /// # Safety
+    ///
+    /// `leaf` must be a valid leaf of the tree
+    unsafe fn insert_leaf_after<'a>(
+        leaf: NonNull<LeafNode<'a, T, A>>,
+        new: <A::Arena as arena::Arena>::Boxed<'a, Node<'a, T, A>>,
+    ) {
+        let leaf = leaf.as_ref();
+        let index = leaf.get_index_in_parent().unwrap();
+        let mut node = leaf.parent;
+        let mut node = node.as_mut();
+        node.children[index].parent_cache = node.children[index].node.cache().into();
+        let mut result = node.insert_at_pos(index + 1, new);
+        while let Err((_, new)) = result {
+            let index = node.get_index_in_parent().unwrap();
+            // result is err, so we're sure parent is valid
+            node = node.parent.unwrap().as_mut();
+            node.children[index].parent_cache = node.children[index].node.cache().into();
+            result = node.insert_at_pos(index + 1, new);
+        }
+    }
+
+    /// Recalculate the caches of the ancestors of `leaf`.
+    ///
+    /// # Safety
+    ///
+    /// `leaf` must be a valid leaf of the tree
+    unsafe fn update_ancestors_cache(leaf: NonNull<LeafNode<'_, T, A>>) {
+        let leaf = leaf.as_ref();
+        let index = leaf.get_index_in_parent().unwrap();
+        let mut node = leaf.parent;
+        let mut node = node.as_mut();
+        node.children[index].parent_cache = node.children[index].node.cache().into();
+        A::update_cache_internal(node, None);
+        while let Some(mut parent) = node.parent {
+            let index = node.get_index_in_parent().unwrap();
+            node = parent.as_mut();
+            node.children[index].parent_cache = node.children[index].node.cache().into();
+            A::update_cache_internal(node, None);
+        }
+    }
+
     pub fn root_cache(&self) -> A::Cache {
         self.with_node(|node| match &**node {
             Node::Internal(node) => node.cache,
@@ -114,6 +211,10 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
     }
 
     /// return a cursor at the given index
+    ///
+    /// The tree is descended by comparing the cumulative caches of the nodes, see
+    /// [`RleTreeTrait::find_pos_internal`]. To search by another dimension, e.g. the
+    /// number of line breaks, implement [`RleTreeTrait`] with a cache of that dimension.
     #[inline]
     pub fn get(&self, mut index: A::Int) -> Option<SafeCursor<'_, T, A>> {
         let mut node = self.root();
@@ -146,6 +247,67 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         }
     }
 
+    /// Like [`RleTree::get`], but returns the element and the offset inside it
+    /// instead of a cursor. Return None if `index` is out of bound.
+    pub fn get_elem(&self, index: A::Int) -> Option<(&T, usize)> {
+        let mut cursor = self.get(index)?;
+        if cursor.offset() >= cursor.leaf().children()[cursor.index()].atom_len() {
+            // the cursor is at the end of an element, move to the start of the next one
+            cursor = cursor.next_elem_start()?;
+        }
+
+        let elem = &cursor.leaf().children()[cursor.index()];
+        Some((elem, cursor.offset()))
+    }
+
+    /// Find the element whose key range contains `key`, by binary searching the tree.
+    ///
+    /// `key_range` maps an element to the range of keys it covers. The elements must
+    /// be sorted by their key ranges and the ranges must not overlap, e.g. spans of
+    /// op ids appended in order. The returned cursor points to the start of the element.
+    pub fn find_by<K: Ord>(
+        &self,
+        key: K,
+        key_range: impl Fn(&T) -> std::ops::Range<K>,
+    ) -> Option<SafeCursor<'_, T, A>> {
+        let mut node = self.root();
+        loop {
+            match node {
+                Node::Internal(internal_node) => {
+                    // the last child that starts at or before `key`
+                    let index = internal_node.children.partition_point(|child| {
+                        child
+                            .node
+                            .get_first_leaf()
+                            .and_then(|leaf| leaf.children.first())
+                            .is_some_and(|first| key_range(first).start <= key)
+                    });
+                    if index == 0 {
+                        return None;
+                    }
+
+                    node = &internal_node.children[index - 1].node;
+                }
+                Node::Leaf(leaf) => {
+                    let index = leaf
+                        .children
+                        .partition_point(|elem| key_range(elem).start <= key);
+                    if index == 0 || !key_range(&leaf.children[index - 1]).contains(&key) {
+                        return None;
+                    }
+
+                    return Some(SafeCursor::from_leaf(
+                        leaf,
+                        index - 1,
+                        0,
+                        Position::Start,
+                        0,
+                    ));
+                }
+            }
+        }
+    }
+
     /// return the first valid cursor after the given index
     /// reviewed by @Leeeon233
     #[inline]
@@ -268,6 +430,28 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         iter::Iter::new(self.root().get_first_leaf())
     }
 
+    /// Iterate over clones of the elements, from the first to the last.
+    ///
+    /// Adjacent elements that can be merged are yielded as one element, so each
+    /// item is a maximal run regardless of how the runs are split across leaves.
+    /// Use [Self::iter] if you need the cursors.
+    pub fn iter_elements(&self) -> impl Iterator<Item = T> + '_ {
+        let mut iter = self.iter().map(|cursor| cursor.get_sliced()).peekable();
+        std::iter::from_fn(move || {
+            let mut elem = iter.next()?;
+            while let Some(next) = iter.next_if(|next| elem.is_mergable(next, &())) {
+                elem.merge(&next, &());
+            }
+            Some(elem)
+        })
+    }
+
+    /// Iterate the elements from the last to the first
+    #[inline]
+    pub fn iter_rev(&self) -> iter::IterRev<'_, T, A> {
+        iter::IterRev::new(self.root().get_last_leaf())
+    }
+
     #[inline]
     pub fn iter_mut(&mut self) -> iter::IterMut<'_, T, A> {
         // SAFETY: the cursor and iter cannot outlive self
@@ -322,6 +506,10 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         });
     }
 
+    /// Iterate the elements in `start..end`, or `start..` if `end` is None.
+    ///
+    /// The elements at both ends are sliced when the bounds fall inside them.
+    ///
     /// reviewed by @Leeeon233
     pub fn iter_range(&self, start: A::Int, end: Option<A::Int>) -> iter::Iter<'_, T, A> {
         let cursor_from = self.get_cursor_ge(start);
@@ -344,6 +532,28 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         }
     }
 
+    /// Mutably iterate the elements in `start..end`, or `start..` if `end` is None.
+    ///
+    /// Like [Self::iter_range], the cursors at both ends only cover the part of the
+    /// element inside the range, see [SafeCursorMut::offset] and the cursor's len.
+    pub fn iter_range_mut(
+        &mut self,
+        start: A::Int,
+        end: Option<A::Int>,
+    ) -> iter::IterMut<'_, T, A> {
+        // SAFETY: the end cursor is only used to stop the iteration. It points into this
+        // tree, which cannot be modified structurally while the iterator borrows it
+        let this = unsafe { &*(self as *const Self) };
+        let end = end
+            .and_then(|end| this.get_cursor_ge(end))
+            .map(|x| UnsafeCursor::new(x.0.leaf, x.0.index, x.0.offset, x.0.pos, 0));
+        let Some(start) = self.get_cursor_ge_mut(start) else {
+            return iter::IterMut::default();
+        };
+
+        iter::IterMut::from_cursor(start, end)
+    }
+
     /// the updated elements will only be notified when the leaf node is split
     pub fn update_at_cursors<U, F>(
         &mut self,
@@ -481,6 +691,66 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         }
     }
 
+    /// Diagnostics of the shape of the tree, e.g. to decide whether it should be rebuilt
+    /// after lots of small edits.
+    pub fn stats(&self) -> TreeStats<A::Int> {
+        let (internal_node_num, leaf_node_num) = self.node_count();
+        let elem_num = self.elem_num();
+        TreeStats {
+            len: self.len(),
+            depth: self.depth(),
+            internal_node_num,
+            leaf_node_num,
+            elem_num,
+            avg_leaf_children: if leaf_node_num == 0 {
+                0.
+            } else {
+                elem_num as f64 / leaf_node_num as f64
+            },
+        }
+    }
+
+    /// The number of levels from the root to the leaves, including both.
+    fn depth(&self) -> usize {
+        self.with_node(|node| {
+            let mut depth = 1;
+            let mut node: &Node<T, A> = node;
+            while let Some(child) = node.as_internal().and_then(|x| x.children().first()) {
+                depth += 1;
+                node = &child.node;
+            }
+            depth
+        })
+    }
+
+    /// Remove all the elements, the tree is reset to the same state as [`RleTree::default`].
+    ///
+    /// The arena is kept, so it can be reused. Note that [`BumpMode`] never frees
+    /// its memory before the tree is dropped.
+    pub fn clear(&mut self) {
+        self.root_mut().as_internal_mut().unwrap().clear();
+    }
+
+    /// Truncate the tree at `index` and return a new tree with the elements from `index`
+    /// onward. The element at the boundary is split.
+    ///
+    /// The elements after `index` are copied into the new tree rather than moved,
+    /// because the nodes are allocated in the arena of `self` (the bump arena in
+    /// `BumpMode`) and cannot be transferred to another tree. So it takes time
+    /// linear to the size of the second half.
+    pub fn split_off(&mut self, index: A::Int) -> Self
+    where
+        T: 'static,
+        A: 'static,
+    {
+        let tail: Self = self
+            .iter_range(index, None)
+            .map(|cursor| cursor.get_sliced())
+            .collect();
+        self.delete_range(Some(index), None);
+        tail
+    }
+
     pub fn debug_check(&mut self) {
         self.with_node_mut(|node| {
             node.as_internal_mut().unwrap().check();
@@ -495,10 +765,33 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
             self.leaf_node_num(),
             self.elem_num(),
             std::mem::size_of::<T>(),
-            self.with_bump(|bump| bump.allocated_bytes())
+            self.allocated_bytes()
         );
     }
 
+    /// The bytes allocated by the arena of this tree.
+    ///
+    /// It's always 0 for [`HeapMode`], use [`RleTree::node_count`] instead.
+    pub fn allocated_bytes(&self) -> usize {
+        self.with_bump(|bump| bump.allocated_bytes())
+    }
+
+    /// Returns the number of (internal nodes, leaf nodes) by walking the tree.
+    pub fn node_count(&self) -> (usize, usize) {
+        self.with_node(|node| {
+            let mut internal = 0;
+            let mut leaf = 0;
+            node.recursive_visit_all(&mut |node| {
+                if node.as_internal().is_some() {
+                    internal += 1;
+                } else {
+                    leaf += 1;
+                }
+            });
+            (internal, leaf)
+        })
+    }
+
     fn internal_node_num(&self) -> usize {
         self.with_node(|node| {
             let mut num = 0;
@@ -544,3 +837,18 @@ impl<T: Rle, A: RleTreeTrait<T>> RleTree<T, A> {
         self.with_node(|node| node.len())
     }
 }
+
+impl<T: Rle, A: RleTreeTrait<T>> Extend<T> for RleTree<T, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.extend_notify(iter, &mut |_, _| {});
+    }
+}
+
+impl<T: Rle + 'static, A: RleTreeTrait<T> + 'static> FromIterator<T> for RleTree<T, A> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::default();
+        tree.extend(iter);
+        tree
+    }
+}
@@ -1,11 +1,14 @@
 use std::{hash::Hash, marker::PhantomData, ptr::NonNull};
 
 use crdt_list::crdt::GetOp;
-use num::FromPrimitive;
+use num::{FromPrimitive, ToPrimitive};
 
 use crate::{HasLength, Rle, RleTreeTrait};
 
-use super::{node::LeafNode, tree_trait::Position};
+use super::{
+    node::{InternalNode, LeafNode},
+    tree_trait::Position,
+};
 
 /// when len > 0, it acts as a selection. When iterating the tree, the len should be the size of the element.
 #[derive(Debug)]
@@ -385,6 +388,52 @@ impl<'tree, T: Rle, A: RleTreeTrait<T>, M> RawSafeCursor<'tree, T, A, M> {
     pub fn get_sliced_with_len(&self, len: usize) -> T {
         self.as_ref().slice(self.0.offset, self.0.offset + len)
     }
+
+    /// The index of the cursor in the tree, in the index unit of `A`
+    #[inline]
+    pub fn get_index(&self) -> A::Int {
+        // SAFETY: SafeCursor is a shared reference to the tree
+        unsafe { self.0.get_index() }
+    }
+
+    /// Compare the positions of two cursors in the tree.
+    ///
+    /// Return `None` if the cursors are from different trees.
+    pub fn cmp<N>(&self, other: &RawSafeCursor<'tree, T, A, N>) -> Option<std::cmp::Ordering> {
+        if !std::ptr::eq(self.root(), other.root()) {
+            return None;
+        }
+
+        Some(self.get_index().cmp(&other.get_index()))
+    }
+
+    /// The distance from `self` to `other` in the index unit of `A`,
+    /// which is positive if `other` is after `self`.
+    ///
+    /// Return `None` if the cursors are from different trees.
+    pub fn distance_to<N>(&self, other: &RawSafeCursor<'tree, T, A, N>) -> Option<isize>
+    where
+        A::Int: ToPrimitive,
+    {
+        if !std::ptr::eq(self.root(), other.root()) {
+            return None;
+        }
+
+        let from = self.get_index().to_isize().unwrap();
+        let to = other.get_index().to_isize().unwrap();
+        Some(to - from)
+    }
+
+    fn root(&self) -> &'tree InternalNode<'tree, T, A> {
+        // SAFETY: SafeCursor has shared reference lifetime to the tree
+        let mut node = unsafe { self.leaf().parent().as_ref() };
+        while let Some(parent) = node.parent() {
+            // SAFETY: parent is valid if node is valid
+            node = unsafe { parent.as_ref() };
+        }
+
+        node
+    }
 }
 
 impl<'tree, T: Rle, A: RleTreeTrait<T>, M> GetOp for RawSafeCursor<'tree, T, A, M> {
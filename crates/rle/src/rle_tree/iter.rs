@@ -17,6 +17,15 @@ pub struct IterMut<'some, T: Rle, A: RleTreeTrait<T>> {
     end_cursor: Option<UnsafeCursor<'some, T, A>>,
 }
 
+/// Iterate the tree backward, from `cursor` (exclusive) to `start_cursor` (inclusive).
+///
+/// `cursor` points to the end of the next element to yield, i.e. its `offset`
+/// is the exclusive end inside the element at `index`.
+pub struct IterRev<'some, T: Rle, A: RleTreeTrait<T>> {
+    cursor: Option<UnsafeCursor<'some, T, A>>,
+    start_cursor: Option<UnsafeCursor<'some, T, A>>,
+}
+
 impl<'tree, T: Rle, A: RleTreeTrait<T>> Default for Iter<'tree, T, A> {
     fn default() -> Self {
         Self {
@@ -113,6 +122,64 @@ impl<'tree, T: Rle, A: RleTreeTrait<T>> Iter<'tree, T, A> {
             end_cursor: end.map(|x| x.0),
         })
     }
+
+    /// Get the element that the next call of [Iterator::next] would return, without advancing.
+    ///
+    /// It respects the end bound the same way.
+    pub fn peek(&self) -> Option<SafeCursor<'tree, T, A>> {
+        Self {
+            cursor: self.cursor.clone(),
+            end_cursor: self.end_cursor.clone(),
+        }
+        .next()
+    }
+}
+
+impl<'tree, T: Rle, A: RleTreeTrait<T>> Default for IterRev<'tree, T, A> {
+    fn default() -> Self {
+        Self {
+            cursor: None,
+            start_cursor: None,
+        }
+    }
+}
+
+impl<'tree, T: Rle, A: RleTreeTrait<T>> IterRev<'tree, T, A> {
+    #[inline]
+    pub fn new(node: Option<&'tree LeafNode<'tree, T, A>>) -> Self {
+        let Some(node) = node else {
+            return Self::default();
+        };
+
+        let Some(last) = node.children.last() else {
+            return Self::default();
+        };
+
+        let index = node.children.len() - 1;
+        Self {
+            cursor: Some(UnsafeCursor::new(
+                node.into(),
+                index,
+                last.atom_len(),
+                Position::End,
+                0,
+            )),
+            start_cursor: None,
+        }
+    }
+
+    /// Iterate backward from `end` (exclusive) to `start` (inclusive).
+    /// If `start` is None, iterate to the beginning of the tree.
+    #[inline]
+    pub fn from_cursor(
+        start: Option<SafeCursor<'tree, T, A>>,
+        end: SafeCursor<'tree, T, A>,
+    ) -> Self {
+        Self {
+            cursor: Some(end.0),
+            start_cursor: start.map(|x| x.0),
+        }
+    }
 }
 
 impl<'tree, T: Rle, A: RleTreeTrait<T>> Iterator for Iter<'tree, T, A> {
@@ -192,6 +259,61 @@ impl<'tree, T: Rle, A: RleTreeTrait<T>> Iterator for Iter<'tree, T, A> {
     }
 }
 
+impl<'tree, T: Rle, A: RleTreeTrait<T>> Iterator for IterRev<'tree, T, A> {
+    type Item = SafeCursor<'tree, T, A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(ref mut cursor) = self.cursor {
+            // SAFETY: we are sure that the cursor is valid
+            let node = unsafe { cursor.leaf.as_ref() };
+            let reach_start = self
+                .start_cursor
+                .as_ref()
+                .is_some_and(|start| start.leaf == cursor.leaf && start.index == cursor.index);
+            let start_offset = if reach_start {
+                self.start_cursor.as_ref().unwrap().offset
+            } else {
+                0
+            };
+
+            let end_offset = cursor.offset;
+            let index = cursor.index;
+            if index > 0 {
+                cursor.index -= 1;
+                cursor.offset = node.children[cursor.index].atom_len();
+            } else {
+                match node.prev() {
+                    Some(prev) => {
+                        cursor.leaf = prev.into();
+                        cursor.index = prev.children.len() - 1;
+                        cursor.offset = prev.children[cursor.index].atom_len();
+                    }
+                    None => self.cursor = None,
+                }
+            }
+
+            if reach_start {
+                self.cursor = None;
+            }
+
+            if start_offset >= end_offset {
+                continue;
+            }
+
+            let child_len = node.children[index].atom_len();
+            return Some(SafeCursor::from_leaf(
+                node,
+                index,
+                start_offset,
+                Position::from_offset(start_offset as isize, child_len),
+                end_offset - start_offset,
+            ));
+        }
+
+        None
+    }
+}
+
 impl<'tree, T: Rle, A: RleTreeTrait<T>> Iterator for IterMut<'tree, T, A> {
     type Item = SafeCursorMut<'tree, T, A>;
 
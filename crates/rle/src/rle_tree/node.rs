@@ -113,6 +113,17 @@ impl<'a, T: Rle, A: RleTreeTrait<T>> Node<'a, T, A> {
         }
     }
 
+    #[inline]
+    pub(crate) fn get_last_leaf_mut(&mut self) -> Option<&mut LeafNode<'a, T, A>> {
+        match self {
+            Self::Internal(node) => node
+                .children
+                .last_mut()
+                .and_then(|child| child.node.get_last_leaf_mut()),
+            Self::Leaf(node) => Some(node),
+        }
+    }
+
     #[inline]
     fn children_num(&self) -> usize {
         match self {
@@ -31,6 +31,12 @@ impl<'a, T: Rle, A: RleTreeTrait<T>> InternalNode<'a, T, A> {
         }
     }
 
+    /// Drop all the children. The allocated capacity of `children` is kept.
+    pub(crate) fn clear(&mut self) {
+        self.children.clear();
+        self.cache = Default::default();
+    }
+
     /// return result need to update cache
     #[inline]
     #[allow(clippy::type_complexity)]
@@ -1,4 +1,4 @@
-use crate::rle_tree::tree_trait::CumulateTreeTrait;
+use crate::rle_tree::{iter::IterRev, tree_trait::CumulateTreeTrait};
 
 use super::super::*;
 use std::ops::Range;
@@ -117,3 +117,313 @@ fn delete_that_causes_increase_levels() {
         tree.debug_check();
     }
 }
+
+#[test]
+fn extend() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).step_by(2).map(|i| i..i + 1));
+    tree.debug_check();
+    assert_eq!(tree.len(), 50);
+    tree.extend((100..200).step_by(2).map(|i| i..i + 1));
+    tree.debug_check();
+    assert_eq!(tree.len(), 100);
+    let mut expected: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    for i in (0..200).step_by(2) {
+        expected.insert(expected.len(), i..i + 1);
+    }
+    assert!(tree
+        .iter()
+        .map(|x| x.as_ref().clone())
+        .eq(expected.iter().map(|x| x.as_ref().clone())));
+
+    // merged into the last element
+    tree.extend([200..201, 201..203]);
+    tree.debug_check();
+    assert_eq!(tree.len(), 103);
+    assert_eq!(tree.iter().last().unwrap().as_ref(), &(200..203));
+    tree.delete_range(Some(10), Some(90));
+    tree.debug_check();
+    assert_eq!(tree.len(), 23);
+}
+
+#[test]
+fn find_by() {
+    // spans of ids with gaps between them, e.g. the ids of one peer's ops
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..300).step_by(3).map(|i| i..i + 2));
+    tree.debug_check();
+    let key_range = |x: &Range<usize>| x.clone();
+    for i in 0..300 {
+        let found = tree.find_by(i, key_range);
+        if i % 3 == 2 {
+            assert!(found.is_none());
+        } else {
+            let start = i / 3 * 3;
+            assert_eq!(found.unwrap().as_ref(), &(start..start + 2));
+        }
+    }
+
+    assert!(tree.find_by(300, key_range).is_none());
+    let empty: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    assert!(empty.find_by(0, key_range).is_none());
+}
+
+#[test]
+fn cursor_cmp_and_distance() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    let a = tree.get(7).unwrap();
+    let b = tree.get(333).unwrap();
+    assert_eq!(a.cmp(&b), Some(std::cmp::Ordering::Less));
+    assert_eq!(b.cmp(&a), Some(std::cmp::Ordering::Greater));
+    assert_eq!(
+        a.cmp(&tree.get(7).unwrap()),
+        Some(std::cmp::Ordering::Equal)
+    );
+    assert_eq!(a.distance_to(&b), Some(326));
+    assert_eq!(b.distance_to(&a), Some(-326));
+
+    let mut other: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    other.insert(0, 0..10);
+    let c = other.get(7).unwrap();
+    assert_eq!(a.cmp(&c), None);
+    assert_eq!(a.distance_to(&c), None);
+}
+
+#[test]
+fn iter_rev() {
+    let empty: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    assert_eq!(empty.iter_rev().count(), 0);
+
+    let mut single: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    single.insert(0, 0..3);
+    single.insert(3, 10..12);
+    let ans: Vec<_> = single.iter_rev().map(|x| x.get_sliced()).collect();
+    assert_eq!(ans, vec![10..12, 0..3]);
+
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    let forward: Vec<_> = tree.iter().map(|x| x.get_sliced()).collect();
+    let mut backward: Vec<_> = tree.iter_rev().map(|x| x.get_sliced()).collect();
+    backward.reverse();
+    assert_eq!(forward, backward);
+
+    // from the middle of an element back to the middle of another
+    let ans: Vec<_> = IterRev::from_cursor(tree.get(7), tree.get(13).unwrap())
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![20..23, 12..15]);
+    // stop exactly at a start bound at the start of an element
+    let ans: Vec<_> = IterRev::from_cursor(tree.get(5), tree.get(13).unwrap())
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![20..23, 10..15]);
+    let ans: Vec<_> = IterRev::from_cursor(None, tree.get(7).unwrap())
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![10..12, 0..5]);
+}
+
+#[test]
+fn iter_range_in_the_middle_of_elements() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    let ans: Vec<_> = tree
+        .iter_range(7, Some(13))
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![12..15, 20..23]);
+    let ans: Vec<_> = tree
+        .iter_range(11, Some(13))
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![21..23]);
+    let ans: Vec<_> = tree
+        .iter_range(5, Some(10))
+        .map(|x| x.get_sliced())
+        .collect();
+    assert_eq!(ans, vec![10..15]);
+    let ans: Vec<_> = tree.iter_range(497, None).map(|x| x.get_sliced()).collect();
+    assert_eq!(ans, vec![992..995]);
+    for start in 0..60 {
+        for end in start..60 {
+            let len: usize = tree
+                .iter_range(start, Some(end))
+                .map(|x| x.get_sliced().len())
+                .sum();
+            assert_eq!(len, end - start, "start={} end={}", start, end);
+        }
+    }
+}
+
+#[test]
+fn memory_stats() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    assert_eq!(tree.node_count(), (1, 0));
+    assert_eq!(tree.allocated_bytes(), 0);
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    let (internal, leaf) = tree.node_count();
+    assert!(internal > 1);
+    assert!(leaf >= 100 / 4);
+
+    let mut bump_tree: RleTree<Range<usize>, CumulateTreeTrait<Range<usize>, 4, BumpMode>> =
+        RleTree::default();
+    bump_tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    assert_eq!(bump_tree.node_count(), (internal, leaf));
+    assert!(bump_tree.allocated_bytes() > 0);
+}
+
+#[test]
+fn clear() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    tree.clear();
+    assert_eq!(tree.len(), 0);
+    assert_eq!(tree.iter().count(), 0);
+    assert_eq!(tree.node_count(), (1, 0));
+    tree.debug_check();
+
+    tree.insert(0, 0..5);
+    tree.insert(5, 10..15);
+    assert_eq!(tree.len(), 10);
+    let ans: Vec<_> = tree.iter().map(|x| x.get_sliced()).collect();
+    assert_eq!(ans, vec![0..5, 10..15]);
+}
+
+#[test]
+fn stats() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    let stats = tree.stats();
+    assert_eq!(stats.len, 0);
+    assert_eq!(stats.depth, 1);
+    assert_eq!(stats.leaf_node_num, 0);
+    assert_eq!(stats.avg_leaf_children, 0.);
+
+    tree.insert(0, 0..5);
+    let stats = tree.stats();
+    assert_eq!(stats.depth, 2);
+    assert_eq!((stats.leaf_node_num, stats.elem_num), (1, 1));
+
+    tree.extend((1..100).map(|i| i * 10..i * 10 + 5));
+    let stats = tree.stats();
+    assert_eq!(stats.len, 500);
+    assert_eq!(stats.elem_num, 100);
+    assert_eq!(
+        (stats.internal_node_num, stats.leaf_node_num),
+        tree.node_count()
+    );
+    assert!(stats.depth > 2);
+    assert!(stats.avg_leaf_children >= 2. && stats.avg_leaf_children <= 4.);
+}
+
+#[test]
+fn fanout_changes_node_count() {
+    fn stats<const N: usize>() -> TreeStats<usize> {
+        let mut tree: RleTree<Range<usize>, CumulateTreeTrait<Range<usize>, N>> =
+            RleTree::default();
+        tree.extend((0..1000).map(|i| i * 10..i * 10 + 5));
+        tree.stats()
+    }
+
+    let small = stats::<4>();
+    let large = stats::<32>();
+    assert_eq!(small.elem_num, large.elem_num);
+    assert!(small.leaf_node_num > large.leaf_node_num);
+    assert!(small.depth > large.depth);
+}
+
+#[test]
+fn get_elem() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    assert_eq!(tree.get_elem(0), None);
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    assert_eq!(tree.get_elem(0), Some((&(0..5), 0)));
+    assert_eq!(tree.get_elem(7), Some((&(10..15), 2)));
+    assert_eq!(tree.get_elem(5), Some((&(10..15), 0)));
+    assert_eq!(tree.get_elem(499), Some((&(990..995), 4)));
+    assert_eq!(tree.get_elem(500), None);
+}
+
+#[test]
+fn iter_elements() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    assert_eq!(tree.iter_elements().count(), 0);
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    tree.extend((0..100).map(|i| 2000 + i * 5..2000 + i * 5 + 5));
+    let elements: Vec<_> = tree.iter_elements().collect();
+    assert_eq!(elements.len(), 101);
+    assert_eq!(elements[0], 0..5);
+    assert_eq!(elements[99], 990..995);
+    assert_eq!(elements[100], 2000..2500);
+}
+
+#[test]
+fn from_iter() {
+    let tree: RleTree<Range<usize>, RangeTreeTrait> =
+        (0..100).map(|i| i * 10..i * 10 + 5).collect();
+    assert_eq!(tree.len(), 500);
+    assert_eq!(tree.iter_elements().count(), 100);
+    // adjacent elements are merged with the last child
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> =
+        (0..100).map(|i| i * 5..i * 5 + 5).collect();
+    assert_eq!(tree.len(), 500);
+    assert_eq!(tree.iter().count(), 1);
+    tree.debug_check();
+}
+
+#[test]
+fn iter_peek() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> = RleTree::default();
+    tree.extend((0..100).map(|i| i * 10..i * 10 + 5));
+    let mut iter = tree.iter_range(12, Some(488));
+    let mut count = 0;
+    while let Some(peeked) = iter.peek() {
+        let next = iter.next().unwrap();
+        assert_eq!(peeked.get_sliced(), next.get_sliced());
+        count += 1;
+    }
+    assert!(iter.next().is_none());
+    assert_eq!(count, 96);
+}
+
+#[test]
+fn iter_range_mut() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> =
+        (0..100).map(|i| i * 10..i * 10 + 5).collect();
+    let mut partial = Vec::new();
+    for mut cursor in tree.iter_range_mut(12, Some(488)) {
+        if cursor.offset() == 0 && cursor.0.len == 5 {
+            let elem = cursor.as_tree_mut();
+            *elem = elem.start + 1000..elem.end + 1000;
+        } else {
+            partial.push((cursor.get_sliced(), cursor.offset()));
+        }
+    }
+    tree.debug_check();
+    assert_eq!(partial, vec![(22..25, 2), (970..973, 0)]);
+    let elements: Vec<_> = tree.iter_elements().collect();
+    assert_eq!(elements[..4], [0..5, 10..15, 20..25, 1030..1035]);
+    assert_eq!(elements[96..], [1960..1965, 970..975, 980..985, 990..995]);
+    assert_eq!(tree.len(), 500);
+}
+
+#[test]
+fn split_off() {
+    let mut tree: RleTree<Range<usize>, RangeTreeTrait> =
+        (0..100).map(|i| i * 10..i * 10 + 5).collect();
+    let mut tail = tree.split_off(12);
+    tree.debug_check();
+    tail.debug_check();
+    assert_eq!(tree.len(), 12);
+    assert_eq!(tail.len(), 488);
+    assert_eq!(
+        tree.iter_elements().collect::<Vec<_>>(),
+        vec![0..5, 10..15, 20..22]
+    );
+    assert_eq!(tail.iter_elements().next(), Some(22..25));
+    assert_eq!(tail.iter_elements().last(), Some(990..995));
+
+    let empty = tail.split_off(488);
+    assert_eq!(empty.len(), 0);
+    assert_eq!(tail.len(), 488);
+}
@@ -145,6 +145,9 @@ pub trait RleTreeTrait<T: Rle>: Sized + Debug {
     fn value_to_update(x: &T) -> Self::CacheInParent;
 }
 
+/// `MAX_CHILD` is the fanout of the tree. A smaller fanout suits large elements,
+/// a larger one keeps the tree shallow when there are many tiny elements.
+/// Use type aliases to pick between a few fanouts.
 #[derive(Debug, Default)]
 pub struct CumulateTreeTrait<T: Rle, const MAX_CHILD: usize, TreeArena: Arena = HeapMode> {
     _phantom: std::marker::PhantomData<(T, TreeArena)>,
@@ -170,11 +173,12 @@ impl<T: Rle, const MAX_CHILD: usize, TreeArena: Arena> RleTreeTrait<T>
 
     fn update_cache_leaf(node: &mut LeafNode<'_, T, Self>) -> isize {
         let old_cache = node.cache;
-        node.cache = node
-            .children()
-            .iter()
-            .map(|x| HasLength::content_len(x))
-            .sum::<usize>() as isize;
+        node.cache = len_to_cache(
+            node.children()
+                .iter()
+                .map(|x| HasLength::content_len(x))
+                .sum::<usize>(),
+        );
         node.cache - old_cache
     }
 
@@ -310,10 +314,22 @@ impl<T: Rle, const MAX_CHILD: usize, TreeArena: Arena> RleTreeTrait<T>
 
     #[inline(always)]
     fn value_to_update(x: &T) -> Self::CacheInParent {
-        x.atom_len() as isize
+        len_to_cache(x.atom_len())
     }
 }
 
+/// The cache of [CumulateTreeTrait] is signed so that it can carry the diff to the parents.
+/// A length beyond `isize::MAX` would wrap into a negative cache silently, so catch it in debug builds.
+#[inline(always)]
+fn len_to_cache(len: usize) -> isize {
+    debug_assert!(
+        len <= isize::MAX as usize,
+        "length {} overflows the cumulative cache",
+        len
+    );
+    len as isize
+}
+
 impl Position {
     #[inline(always)]
     pub fn get_pos(index: usize, len: usize) -> Position {
@@ -17,10 +17,11 @@ use crate::{rle_trait::HasIndex, HasLength, Mergable, SearchResult, SliceIterato
 /// 1. (merged) It refers to the index of the merged element.
 /// 2. (atom) The index of substantial elements. It refers to the index of the atom element.
 ///
-/// By default, we use atom index in RleVec.
-/// - len() returns the number of atom elements in the array.
-/// - get(index) returns the atom element at the index.
-/// - slice(from, to) returns a slice of atom elements from the index from to the index to.
+/// The public API of RleVec is stable, so it can be used to process histories outside of loro:
+/// - [RleVec::new] and [RleVec::push] build the vector, merging the pushed element when possible.
+/// - [RleVec::len] and [RleVec::iter] work on the merged elements.
+/// - [RleVec::atom_len] and [RleVec::get_by_atom_index] work on the atom index, i.e. [HasIndex].
+/// - [Sliceable::slice] slices by the accumulated atom length of the elements.
 pub struct RleVec<A: Array> {
     _p: PhantomData<fn() -> A::Item>,
     vec: SmallVec<A>,
@@ -125,6 +126,7 @@ impl<A: Array> RleVec<A> {
         self.vec.is_empty()
     }
 
+    /// Create an empty vector
     #[inline]
     pub fn new() -> Self {
         RleVec {
@@ -146,7 +148,8 @@ impl<A: Array> RleVec<A> {
         self.vec.capacity()
     }
 
-    /// this is the length of merged elements
+    /// The number of merged elements, not the number of atoms.
+    /// Use [RleVec::atom_len] to get the latter.
     pub fn len(&self) -> usize {
         self.vec.len()
     }
@@ -326,13 +329,13 @@ where
 
     /// Get the element at the given atom index.
     ///
-    /// If the index is larger than the end index, return None.
+    /// If the vector is empty or the index is larger than the end index, return None.
     /// Otherwise, return: Some((element, merged_index, offset))
     pub fn get_by_atom_index(
         &self,
         index: <A::Item as HasIndex>::Int,
     ) -> Option<SearchResult<'_, A::Item, <A::Item as HasIndex>::Int>> {
-        if index > self.end() {
+        if self.vec.is_empty() || index > self.end() {
             return None;
         }
 
@@ -414,6 +417,7 @@ where
         &self.vec[range]
     }
 
+    /// The distance between the start index of the first element and the end index of the last element
     pub fn atom_len(&self) -> <A::Item as HasIndex>::Int {
         self.vec
             .last()
@@ -498,6 +502,7 @@ impl<A: Array> RleVec<A> {
         &mut self.vec
     }
 
+    /// Iterate over the merged elements
     #[inline(always)]
     pub fn iter(&self) -> std::slice::Iter<'_, A::Item> {
         self.vec.iter()
@@ -675,4 +680,27 @@ mod test {
             println!("{:?}", k);
         }
     }
+
+    #[test]
+    fn public_api() {
+        let mut a: RleVec<[Range<usize>; 2]> = RleVec::new();
+        assert!(a.is_empty());
+        assert_eq!(a.atom_len(), 0);
+        assert!(a.get_by_atom_index(0).is_none());
+
+        a.push(0..3);
+        a.push(3..5);
+        a.push(8..10);
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.atom_len(), 10);
+        assert_eq!(a.iter().cloned().collect::<Vec<_>>(), vec![0..5, 8..10]);
+
+        let found = a.get_by_atom_index(9).unwrap();
+        assert_eq!(found.element, &(8..10));
+        assert_eq!(found.merged_index, 1);
+        assert_eq!(found.offset, 1);
+        assert!(a.get_by_atom_index(11).is_none());
+
+        assert_eq!(&*a.slice(2, 6), &vec![2..5, 8..9]);
+    }
 }